@@ -0,0 +1,51 @@
+//! Local `Rule34` compatible api
+//!
+//! ```text
+//! shuller-serve [--addr 127.0.0.1:8080] [--posts posts.json]... [--generate 1000]
+//! ```
+
+use shuller::serve::Server;
+use shuller::testing::FakeBooru;
+
+const USAGE: &str =
+    "usage: shuller-serve [--addr 127.0.0.1:8080] [--posts posts.json]... [--generate 1000]";
+
+fn main() {
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut booru = FakeBooru::init();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            exit(USAGE);
+        };
+        booru = match arg.as_str() {
+            "--addr" => {
+                addr = value;
+                booru
+            }
+            "--posts" => {
+                let json =
+                    std::fs::read(&value).unwrap_or_else(|x| exit(&format!("{}: {}", value, x)));
+                booru
+                    .json(&json)
+                    .unwrap_or_else(|x| exit(&format!("{}: {}", value, x)))
+            }
+            "--generate" => booru.generate(value.parse().unwrap_or_else(|_| exit(USAGE))),
+            _ => exit(USAGE),
+        };
+    }
+    let runtime = tokio::runtime::Runtime::new().unwrap_or_else(|x| exit(&x.to_string()));
+    let result = runtime.block_on(async {
+        let server = Server::bind(&addr, booru).await?;
+        println!("serving {}", server.base_url());
+        server.run().await
+    });
+    if let Err(error) = result {
+        exit(&error.to_string());
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
+}
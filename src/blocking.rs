@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::error::Result;
+use crate::rules::rule34::blacklist::Blacklist;
+use crate::rules::rule34::client::R34Client;
+use crate::rules::rule34::data::{Post, Posts};
+use crate::rules::rule34::pages::Pages;
+use crate::rules::rule34::params::R34Params;
+
+/// Runtime of all blocking calls, its one worker keeps pooled connections alive
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("shuller-blocking")
+            .enable_all()
+            .build()
+            .expect("Failed to build tokio runtime")
+    })
+}
+
+/// Run `future` on internal runtime
+///
+/// **Panics** if it's called inside async runtime
+#[inline]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+/// Blocking version of [Pages]
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// fn example() {
+///     for posts in R34Params::init().positive_tags(vec!["dark"]).limit(100).pages_blocking() {
+///         println!("{}", posts.unwrap().len());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlockingPages(Pages);
+
+impl BlockingPages {
+    /// Drop posts blocked by [Blacklist] from every page, see [Pages::blacklist]
+    #[inline]
+    pub fn blacklist(self, blacklist: Blacklist) -> Self {
+        Self(self.0.blacklist(blacklist))
+    }
+}
+
+impl Iterator for BlockingPages {
+    type Item = Result<Posts>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.0.next())
+    }
+}
+
+/// Blocking versions of requests
+///
+/// **Panics** if they're called inside async runtime
+impl R34Client {
+    /// Blocking version of [R34Client::download]
+    #[inline]
+    pub fn download_blocking(&self, params: &R34Params<'_>) -> Result<Posts> {
+        block_on(self.download(params))
+    }
+
+    /// Blocking version of [R34Client::count]
+    #[inline]
+    pub fn count_blocking(&self, params: &R34Params<'_>) -> Result<u64> {
+        block_on(self.count(params))
+    }
+
+    /// Blocking version of [R34Client::random]
+    #[inline]
+    pub fn random_blocking(&self, params: &R34Params<'_>) -> Result<Option<Post>> {
+        block_on(self.random(params))
+    }
+
+    /// Blocking version of [R34Client::pages]
+    #[inline]
+    pub fn pages_blocking(&self, params: &R34Params<'_>) -> BlockingPages {
+        BlockingPages(self.pages(params))
+    }
+}
+
+/// Blocking versions of requests, they go through [R34Client::global]
+///
+/// **Panics** if they're called inside async runtime
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// fn example() {
+///     let posts = R34Params::init()
+///         .positive_tags(vec!["dark", "fish"])
+///         .limit(3)
+///         .download_blocking()
+///         .unwrap();
+/// }
+/// ```
+impl R34Params<'_> {
+    /// Blocking version of [JsonDownload::download](uller::JsonDownload::download)
+    #[inline]
+    pub fn download_blocking(&self) -> Result<Posts> {
+        R34Client::global().download_blocking(self)
+    }
+
+    /// Blocking version of [R34Params::count]
+    #[inline]
+    pub fn count_blocking(&self) -> Result<u64> {
+        R34Client::global().count_blocking(self)
+    }
+
+    /// Blocking version of [R34Params::random]
+    #[inline]
+    pub fn random_blocking(&self) -> Result<Option<Post>> {
+        R34Client::global().random_blocking(self)
+    }
+
+    /// Blocking version of [R34Params::pages]
+    #[inline]
+    pub fn pages_blocking(&self) -> BlockingPages {
+        R34Client::global().pages_blocking(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::transport::{Request, Response, StatusCode, Transport};
+
+    #[derive(Debug)]
+    struct Fixed;
+
+    #[async_trait]
+    impl Transport for Fixed {
+        async fn send(&self, request: Request) -> Result<Response> {
+            if request
+                .url
+                .query_pairs()
+                .any(|(k, v)| k == "json" && v == "0")
+            {
+                return Ok(Response::new(StatusCode::OK, r#"<posts count="3">"#));
+            }
+            let body = include_str!("../tests/fixtures/posts.json");
+            Ok(Response::new(StatusCode::OK, body))
+        }
+    }
+
+    #[test]
+    fn blocking() {
+        let client = R34Client::init().transport(Fixed);
+        let params = R34Params::init().limit(3);
+        assert_eq!(client.download_blocking(&params).unwrap().len(), 3);
+        assert_eq!(client.count_blocking(&params).unwrap(), 3);
+        assert!(client.random_blocking(&params).unwrap().is_some());
+        // every page is full, so stop by hand
+        assert_eq!(client.pages_blocking(&params).take(2).count(), 2);
+    }
+}
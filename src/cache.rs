@@ -0,0 +1,419 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use uller::Url;
+
+use crate::rules::rule34::credentials::redact_credentials;
+use crate::transport::header::{self, HeaderMap};
+
+/// How request uses [Cache]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheMode {
+    /// Use fresh entry, revalidate stale entry, store response
+    #[default]
+    Default,
+    /// Don't read and don't store anything
+    Bypass,
+    /// Don't use fresh entry, but revalidate it and store response
+    Refresh,
+}
+
+/// Cached body of response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub body: Bytes,
+    /// `ETag` header of response
+    pub etag: Option<String>,
+    /// `Last-Modified` header of response
+    pub last_modified: Option<String>,
+    /// Entry is stale after this time
+    pub expires: SystemTime,
+}
+
+impl CacheEntry {
+    /// Make entry from response which expires after `ttl`
+    pub fn new(body: impl Into<Bytes>, headers: &HeaderMap, ttl: Duration) -> Self {
+        let header = |name: header::HeaderName| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            body: body.into(),
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+            expires: expires_after(ttl),
+        }
+    }
+
+    /// Check if entry isn't expired
+    #[inline]
+    pub fn is_fresh(&self) -> bool {
+        self.expires > SystemTime::now()
+    }
+
+    /// Check if entry can be revalidated by `ETag` or `Last-Modified`
+    #[inline]
+    pub fn can_revalidate(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// Memory part of [Cache]
+#[derive(Debug, Default)]
+struct Memory {
+    /// Entry and tick of its last use
+    entries: HashMap<String, (CacheEntry, u64)>,
+    /// Keys by tick of last use, the first one is least recently used
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Memory {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &str) -> Option<CacheEntry> {
+        let tick = self.next_tick();
+        let (entry, used) = self.entries.get_mut(key)?;
+        let old = std::mem::replace(used, tick);
+        let entry = entry.clone();
+        self.order.remove(&old);
+        self.order.insert(tick, key.to_string());
+        Some(entry)
+    }
+
+    /// Insert entry, least recently used entries are evicted over `capacity`
+    fn insert(&mut self, key: &str, entry: CacheEntry, capacity: usize) {
+        let tick = self.next_tick();
+        if let Some((_, old)) = self.entries.insert(key.to_string(), (entry, tick)) {
+            self.order.remove(&old);
+        }
+        self.order.insert(tick, key.to_string());
+        while self.entries.len() > capacity {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((_, used)) = self.entries.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// Header of file of [Cache] on disk, body of response goes after it
+#[derive(Serialize, Deserialize)]
+struct DiskHeader {
+    /// Url of entry without credentials, files are named by hash which may collide
+    #[serde(default)]
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    expires_ms: u64,
+}
+
+/// Cache of responses keyed by url without `api_key` and `user_id`, with TTL, LRU memory bound and optional disk backend
+///
+/// Cheap to clone, all clones share the same entries
+///
+/// ```
+/// use std::time::Duration;
+/// use shuller::prelude::*;
+///
+/// let cache = Cache::new(100)
+///     .ttl(Duration::from_secs(30))
+///     .disk(std::env::temp_dir().join("shuller-cache"));
+/// let client = R34Client::init().cache(cache);
+/// // skip cache for one request
+/// let fresh = client.clone().cache_mode(CacheMode::Bypass);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cache {
+    capacity: usize,
+    ttl: Duration,
+    disk: Option<PathBuf>,
+    memory: Arc<Mutex<Memory>>,
+}
+
+impl Cache {
+    /// Make cache which keeps up to `capacity` entries in memory, entries live 60s
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: Duration::from_secs(60),
+            disk: None,
+            memory: Default::default(),
+        }
+    }
+
+    /// Set time for which new entries are fresh, [Duration::MAX] means they never expire
+    #[inline]
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Also keep entries in files of `dir`
+    #[inline]
+    pub fn disk(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk = Some(dir.into());
+        self
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Make entry of response with TTL of cache
+    #[inline]
+    pub fn entry(&self, body: impl Into<Bytes>, headers: &HeaderMap) -> CacheEntry {
+        CacheEntry::new(body, headers, self.ttl)
+    }
+
+    /// Make `entry` fresh again after `304 Not Modified`
+    #[inline]
+    pub fn revalidated(&self, entry: CacheEntry) -> CacheEntry {
+        CacheEntry {
+            expires: expires_after(self.ttl),
+            ..entry
+        }
+    }
+
+    /// Get entry of `url`, even stale one
+    pub async fn get(&self, url: &Url) -> Option<CacheEntry> {
+        let url = &redact_credentials(url);
+        if let Some(entry) = self.memory_get(url.as_str()) {
+            return Some(entry);
+        }
+        let entry = read_file(&self.path(url)?, url).await?;
+        self.memory_insert(url.as_str(), entry.clone());
+        Some(entry)
+    }
+
+    /// Store entry of `url`
+    pub async fn insert(&self, url: &Url, entry: CacheEntry) {
+        let url = &redact_credentials(url);
+        if let Some(path) = self.path(url) {
+            if let Err(error) = write_file(&path, url, &entry).await {
+                tracing::warn!(path = %path.display(), error = %error, "failed to write cache");
+            }
+        }
+        self.memory_insert(url.as_str(), entry);
+    }
+
+    /// Remove entry of `url`
+    pub async fn invalidate(&self, url: &Url) {
+        let url = &redact_credentials(url);
+        self.lock().remove(url.as_str());
+        if let Some(path) = self.path(url) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+
+    /// Remove all entries
+    pub async fn clear(&self) {
+        self.lock().clear();
+        let Some(dir) = &self.disk else {
+            return;
+        };
+        let Ok(mut files) = tokio::fs::read_dir(dir).await else {
+            return;
+        };
+        while let Ok(Some(file)) = files.next_entry().await {
+            if file.path().extension().is_some_and(|x| x == "cache") {
+                let _ = tokio::fs::remove_file(file.path()).await;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Memory> {
+        self.memory.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    #[inline]
+    fn memory_get(&self, key: &str) -> Option<CacheEntry> {
+        self.lock().get(key)
+    }
+
+    fn memory_insert(&self, key: &str, entry: CacheEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.lock().insert(key, entry, self.capacity);
+    }
+
+    /// File of `url` on disk, named by FNV hash of url
+    fn path(&self, url: &Url) -> Option<PathBuf> {
+        let mut hasher = fnv::FnvHasher::default();
+        hasher.write(url.as_str().as_bytes());
+        Some(
+            self.disk
+                .as_ref()?
+                .join(format!("{:016x}.cache", hasher.finish())),
+        )
+    }
+}
+
+/// Time after `ttl` from now, huge `ttl` like [Duration::MAX] means about 100 years
+fn expires_after(ttl: Duration) -> SystemTime {
+    let now = SystemTime::now();
+    now.checked_add(ttl)
+        .unwrap_or_else(|| now + Duration::from_secs(100 * 365 * 86400))
+}
+
+/// Read entry of `url`, [None] if file is missing, broken or has other url
+async fn read_file(path: &Path, url: &Url) -> Option<CacheEntry> {
+    let data = tokio::fs::read(path).await.ok()?;
+    let split = data.iter().position(|x| *x == b'\n')?;
+    let header: DiskHeader = serde_json::from_slice(&data[..split]).ok()?;
+    if header.url != url.as_str() {
+        return None;
+    }
+    Some(CacheEntry {
+        body: Bytes::copy_from_slice(&data[split + 1..]),
+        etag: header.etag,
+        last_modified: header.last_modified,
+        expires: SystemTime::UNIX_EPOCH.checked_add(Duration::from_millis(header.expires_ms))?,
+    })
+}
+
+/// Write entry of `url` into temporary file and rename it, so readers never see half of file
+async fn write_file(path: &Path, url: &Url, entry: &CacheEntry) -> std::io::Result<()> {
+    static TEMP: AtomicU64 = AtomicU64::new(0);
+    let header = DiskHeader {
+        url: url.to_string(),
+        etag: entry.etag.clone(),
+        last_modified: entry.last_modified.clone(),
+        expires_ms: entry
+            .expires
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    };
+    let mut data = serde_json::to_vec(&header)?;
+    data.push(b'\n');
+    data.extend_from_slice(&entry.body);
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let temp = path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    tokio::fs::write(&temp, data).await?;
+    if let Err(error) = tokio::fs::rename(&temp, path).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    fn url(n: u32) -> Url {
+        Url::parse(&format!("https://api.rule34.xxx/index.php?pid={}", n)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn lru() {
+        let cache = Cache::new(2);
+        for n in 0..2 {
+            cache
+                .insert(&url(n), cache.entry("[]", &HeaderMap::new()))
+                .await;
+        }
+        // 0 is used, so 1 is evicted
+        assert!(cache.get(&url(0)).await.is_some());
+        cache
+            .insert(&url(2), cache.entry("[]", &HeaderMap::new()))
+            .await;
+        assert!(cache.get(&url(1)).await.is_none());
+        assert!(cache.get(&url(0)).await.is_some());
+        assert!(cache.get(&url(2)).await.is_some());
+
+        cache.invalidate(&url(0)).await;
+        assert!(cache.get(&url(0)).await.is_none());
+        let memory = cache.lock();
+        assert_eq!(memory.entries.len(), memory.order.len());
+    }
+
+    #[tokio::test]
+    async fn ttl() {
+        let cache = Cache::new(2).ttl(Duration::ZERO);
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, "\"abc\"".parse().unwrap());
+        cache.insert(&url(0), cache.entry("[]", &headers)).await;
+        let entry = cache.get(&url(0)).await.unwrap();
+        assert!(!entry.is_fresh());
+        assert!(entry.can_revalidate());
+        assert_eq!(entry.etag.as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn ttl_max() {
+        let cache = Cache::new(1).ttl(Duration::MAX);
+        let entry = cache.entry("[]", &HeaderMap::new());
+        assert!(entry.is_fresh());
+        assert!(cache.revalidated(entry).is_fresh());
+    }
+
+    #[tokio::test]
+    async fn disk() {
+        let dir = TempDir::new("cache");
+        let cache = Cache::new(0).disk(&*dir);
+        cache
+            .insert(&url(0), cache.entry("[1]\n[2]", &HeaderMap::new()))
+            .await;
+
+        let other = Cache::new(1).disk(&*dir);
+        let entry = other.get(&url(0)).await.unwrap();
+        assert_eq!(entry.body, Bytes::from("[1]\n[2]"));
+        assert!(entry.is_fresh());
+
+        // file of other url with the same name isn't used
+        let path = cache.path(&url(0)).unwrap();
+        std::fs::copy(&path, cache.path(&url(1)).unwrap()).unwrap();
+        assert!(other.get(&url(1)).await.is_none());
+        let names: Vec<_> = std::fs::read_dir(&*dir).unwrap().collect();
+        assert_eq!(names.len(), 2);
+
+        // credentials never reach disk
+        let secret =
+            Url::parse("https://api.rule34.xxx/index.php?pid=5&api_key=secret&user_id=987654321")
+                .unwrap();
+        cache
+            .insert(&secret, cache.entry("[]", &HeaderMap::new()))
+            .await;
+        assert!(other.get(&secret).await.is_some());
+        for file in std::fs::read_dir(&*dir).unwrap() {
+            let data = std::fs::read_to_string(file.unwrap().path()).unwrap();
+            assert!(!data.contains("secret") && !data.contains("987654321"));
+        }
+
+        other.clear().await;
+        assert!(cache.get(&url(0)).await.is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use md5::Digest;
+use tokio::io::AsyncReadExt;
+
+/// Streaming md5 of file, the same as `hash` of post
+///
+/// md5 isn't secure, it's used only to find broken files
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Md5(md5::Md5);
+
+impl Md5 {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `data` to hashed bytes
+    #[inline]
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Lowercase hex of md5 of all added bytes
+    #[inline]
+    pub(crate) fn hex(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+
+    /// Add all bytes of file at `path`
+    pub(crate) async fn update_file(&mut self, path: &Path) -> std::io::Result<()> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            match file.read(&mut buf).await? {
+                0 => return Ok(()),
+                read => self.update(&buf[..read]),
+            }
+        }
+    }
+}
+
+/// Check if `hash` looks like md5
+pub(crate) fn is_md5(hash: &str) -> bool {
+    hash.len() == 32 && hash.bytes().all(|x| x.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn hash() {
+        assert!(is_md5("D41d8cd98f00b204e9800998ecf8427e"));
+        assert!(!is_md5("d41d8cd98f00b204"));
+    }
+
+    #[tokio::test]
+    async fn file() {
+        let dir = TempDir::new("checksum");
+        let path = dir.join("fox.txt");
+        std::fs::create_dir_all(&*dir).unwrap();
+        std::fs::write(&path, "The quick brown fox jumps over the lazy dog").unwrap();
+        let mut md5 = Md5::new();
+        md5.update_file(&path).await.unwrap();
+        assert_eq!(md5.hex(), "9e107d9d372bb6826bd81d3542a419d6");
+    }
+}
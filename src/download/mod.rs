@@ -0,0 +1,1475 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinSet;
+use uller::Url;
+
+use crate::error::{Error, Result};
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::rules::rule34::client::R34Client;
+use crate::rules::rule34::credentials::redact_url;
+use crate::rules::rule34::data::{Post, Posts};
+use crate::rules::rule34::mini_data::MiniPosts;
+use crate::rules::rule34::pages::Pages;
+use crate::rules::rule34::tag_type::{TagType, TagTypes};
+use crate::transport::{header, Request, ReqwestTransport, StatusCode, Transport};
+use checksum::{is_md5, Md5};
+use resume::{ContentRange, PartMeta};
+pub use sidecar::Sidecar;
+pub use template::{Collision, Template, TemplateError};
+
+mod checksum;
+mod resume;
+mod sidecar;
+mod template;
+
+/// Extension of unfinished files
+const PART: &str = "part";
+/// Directory of files with wrong md5, inside directory of file
+const QUARANTINE: &str = ".quarantine";
+
+/// File of post to save
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rendition {
+    /// Raw file, `file_url`
+    #[default]
+    File,
+    /// Resized picture, `sample_url`, raw file if post has no sample
+    Sample,
+    /// Picture in miniature, `preview_url`
+    Preview,
+}
+
+impl Rendition {
+    /// Url of rendition of `post`
+    pub fn url<'a>(&self, post: &'a Post) -> &'a str {
+        match self {
+            Self::File => &post.file_url,
+            Self::Sample if post.sample_url.is_empty() => &post.file_url,
+            Self::Sample => &post.sample_url,
+            Self::Preview => &post.preview_url,
+        }
+    }
+}
+
+/// What to do with file which md5 isn't `hash` of post
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OnMismatch {
+    /// Download file again by [RetryPolicy], it fails with [Error::Checksum] after the last attempt
+    #[default]
+    Retry,
+    /// Move file into `.quarantine` directory next to it, it fails with [Error::Checksum]
+    Quarantine,
+    /// Save file anyway with warning, see [Saved::verified]
+    Keep,
+}
+
+/// Choice of [Rendition] for every post
+#[derive(Clone)]
+struct RenditionChoice(Arc<dyn Fn(&Post) -> Rendition + Send + Sync>);
+
+impl Debug for RenditionChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RenditionChoice")
+    }
+}
+
+/// Saves media of posts into directory
+///
+/// Files are written into `.part` file next to target and renamed when they're complete,
+/// so target file is never half written. Broken downloads are continued, see [Downloader::resume]
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::download::{Downloader, Rendition};
+///
+/// async fn example() {
+///     let posts = R34Params::init().positive_tags(vec!["dark"]).limit(10).download().await.unwrap();
+///     let report = Downloader::new("media")
+///         .concurrency(4)
+///         .rendition_with(|post| match post.media_kind() {
+///             MediaKind::Video => Rendition::Preview,
+///             _ => Rendition::Sample,
+///         })
+///         .download(&posts)
+///         .await;
+///     println!("{}", report);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    dir: PathBuf,
+    transport: Arc<dyn Transport>,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    concurrency: usize,
+    rendition: RenditionChoice,
+    skip_existing: bool,
+    resume: bool,
+    verify: bool,
+    on_mismatch: OnMismatch,
+    template: Template,
+    tag_types: Arc<TagTypes>,
+    sidecars: Vec<Sidecar>,
+}
+
+impl Downloader {
+    /// Max number of files which are downloaded at once by default
+    pub const DEFAULT_CONCURRENCY: usize = 4;
+
+    /// Save files into `dir`, it's created if it doesn't exist
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            transport: Arc::new(ReqwestTransport::default()),
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
+            concurrency: Self::DEFAULT_CONCURRENCY,
+            rendition: RenditionChoice(Arc::new(|_| Rendition::File)),
+            skip_existing: true,
+            resume: true,
+            verify: true,
+            on_mismatch: OnMismatch::Retry,
+            template: Template::default(),
+            tag_types: Default::default(),
+            sidecars: vec![],
+        }
+    }
+
+    /// Set [Transport] for every file
+    #[inline]
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Set [RetryPolicy] for every file, attempts continue `.part` file if [Downloader::resume] is on
+    #[inline]
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set [RateLimiter] for every file
+    #[inline]
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Set max number of files which are downloaded at once, at least `1`
+    #[inline]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Save the same [Rendition] of every post
+    #[inline]
+    pub fn rendition(self, rendition: Rendition) -> Self {
+        self.rendition_with(move |_| rendition)
+    }
+
+    /// Choose [Rendition] for every post
+    #[inline]
+    pub fn rendition_with(
+        mut self,
+        choice: impl Fn(&Post) -> Rendition + Send + Sync + 'static,
+    ) -> Self {
+        self.rendition = RenditionChoice(Arc::new(choice));
+        self
+    }
+
+    /// Set if existing files are skipped, otherwise they're replaced
+    ///
+    /// Skipped file must belong to post, it's checked by [Sidecar::Json] and md5 if
+    /// [Downloader::verify] is on, so file of other post with the same path isn't taken
+    #[inline]
+    pub fn skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+
+    /// Set if `.part` files of failed downloads are kept and continued by `Range` requests
+    ///
+    /// `.part` is continued only if strong `ETag` or size of remote file is known and didn't change,
+    /// otherwise file is downloaded from start
+    #[inline]
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Set if md5 of [Rendition::File] is checked against `hash` of post while it's downloaded
+    ///
+    /// Samples, previews and posts without `hash` are never checked
+    #[inline]
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Set what to do with file which md5 isn't `hash` of post
+    ///
+    /// ```
+    /// use shuller::download::{Downloader, OnMismatch};
+    ///
+    /// let downloader = Downloader::new("media").on_mismatch(OnMismatch::Quarantine);
+    /// ```
+    #[inline]
+    pub fn on_mismatch(mut self, on_mismatch: OnMismatch) -> Self {
+        self.on_mismatch = on_mismatch;
+        self
+    }
+
+    /// Set path of files inside [Downloader::dir], it's [Template::DEFAULT] by default
+    ///
+    /// ```
+    /// use shuller::download::Downloader;
+    ///
+    /// let downloader = Downloader::new("media").template("{uploader}/{id}_{md5}.{ext}".parse().unwrap());
+    /// ```
+    #[inline]
+    pub fn template(mut self, template: Template) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Set [TagTypes] which find `{artist}` of [Downloader::template]
+    ///
+    /// [Downloader::download_pages] gets missing types by client of pages
+    #[inline]
+    pub fn tag_types(mut self, tag_types: TagTypes) -> Self {
+        self.tag_types = Arc::new(tag_types);
+        self
+    }
+
+    /// Write metadata of post in `format` next to every file, like `123.png.json`
+    ///
+    /// Sidecar is written into `.part` file and renamed right after file, so there's no file
+    /// without sidecars. Missing sidecars of skipped files are added
+    ///
+    /// ```
+    /// use shuller::download::{Downloader, Sidecar};
+    ///
+    /// let downloader = Downloader::new("media").sidecar(Sidecar::Json).sidecar(Sidecar::Xmp);
+    /// ```
+    #[inline]
+    pub fn sidecar(mut self, format: Sidecar) -> Self {
+        if !self.sidecars.contains(&format) {
+            self.sidecars.push(format);
+        }
+        self
+    }
+
+    /// Directory of saved files
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Url of chosen [Rendition] of `post`
+    #[inline]
+    pub fn url<'a>(&self, post: &'a Post) -> &'a str {
+        (self.rendition.0)(post).url(post)
+    }
+
+    /// Path of file of `post` by [Downloader::template], like `dir/123.png`
+    #[inline]
+    pub fn path(&self, post: &Post) -> PathBuf {
+        let url = self.url(post);
+        self.dir
+            .join(self.template.render_with(post, url, &self.tag_types))
+    }
+
+    /// Save files of `posts`
+    #[inline]
+    pub async fn download(&self, posts: &Posts) -> Report {
+        self.download_iter(posts.iter().cloned()).await
+    }
+
+    /// Save files of `posts`
+    #[inline]
+    pub async fn download_mini(&self, posts: &MiniPosts<'_>) -> Report {
+        self.download_iter(posts.as_ref().iter().map(|x| x.post().clone()))
+            .await
+    }
+
+    /// Save files of all pages, one page after another
+    ///
+    /// Stops at first error of page, it's kept in [Report::page_error] with entries of pages
+    /// before it, saved files stay and are skipped next time.
+    /// Collisions of paths are found across all pages, like in [Downloader::download_iter]
+    ///
+    /// Input is [Pages], posts of other sources go to [Downloader::download_iter].
+    /// Types of tags for `{artist}` are got by client of pages, see [R34Client::tag_types]
+    pub async fn download_pages(&self, mut pages: Pages) -> Report {
+        let mut this = self.clone();
+        let mut report = Report::default();
+        let mut paths = HashMap::new();
+        while let Some(posts) = pages.next().await {
+            let posts = match this.template.has_artist() {
+                true => match posts {
+                    Ok(posts) => this
+                        .add_tag_types(pages.client(), &posts)
+                        .await
+                        .map(|_| posts),
+                    Err(error) => Err(error),
+                },
+                false => posts,
+            };
+            match posts {
+                Ok(posts) => report.merge(this.download_with(posts, &mut paths).await),
+                Err(error) => {
+                    tracing::warn!(error = %error, "page isn't downloaded");
+                    report.page_error = Some(error);
+                    break;
+                }
+            }
+        }
+        report
+    }
+
+    /// Get types of tags of `posts` which aren't known yet
+    async fn add_tag_types(&mut self, client: &R34Client, posts: &Posts) -> Result<()> {
+        let mut missing: Vec<&str> = posts
+            .iter()
+            .flat_map(|x| x.tags.split_whitespace())
+            .filter(|x| !self.tag_types.contains(x))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        missing.sort_unstable();
+        missing.dedup();
+        let found = client.tag_types(missing.iter().copied()).await?;
+        let types = Arc::make_mut(&mut self.tag_types);
+        types.extend(found);
+        // tags unknown to api aren't requested again
+        for tag in missing {
+            if !types.contains(tag) {
+                types.insert(tag, TagType::General);
+            }
+        }
+        Ok(())
+    }
+
+    /// Save files of `posts`, at most [Downloader::concurrency] at once
+    ///
+    /// Entries of [Report] are in order of `posts`. If [Downloader::template] gives the same path
+    /// to several posts, the first one is saved and others fail, see [Template::collisions]
+    #[inline]
+    pub async fn download_iter(&self, posts: impl IntoIterator<Item = Post>) -> Report {
+        self.download_with(posts, &mut HashMap::new()).await
+    }
+
+    /// Save files of `posts`, `paths` has ids of posts which took paths before
+    async fn download_with(
+        &self,
+        posts: impl IntoIterator<Item = Post>,
+        paths: &mut HashMap<PathBuf, i64>,
+    ) -> Report {
+        let mut tasks = JoinSet::new();
+        let mut outcomes = vec![];
+        for (index, post) in posts.into_iter().enumerate() {
+            let path = self.path(&post);
+            if let Some(first) = paths.get(&path).filter(|x| **x != post.id) {
+                let error = std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("path is taken by post {}", first),
+                );
+                tracing::warn!(id = post.id, path = %path.display(), error = %error, "file isn't saved");
+                outcomes.push((
+                    index,
+                    Outcome::Failed(Failed {
+                        id: post.id,
+                        url: self.url(&post).to_string(),
+                        path,
+                        error: error.into(),
+                    }),
+                ));
+                continue;
+            }
+            paths.insert(path, post.id);
+            if tasks.len() >= self.concurrency {
+                outcomes.extend(join_next(&mut tasks).await);
+            }
+            let this = self.clone();
+            tasks.spawn(async move { (index, this.save(post).await) });
+        }
+        while let Some(outcome) = join_next(&mut tasks).await {
+            outcomes.push(outcome);
+        }
+        outcomes.sort_by_key(|(index, _)| *index);
+        let mut report = Report::default();
+        for (_, outcome) in outcomes {
+            report.push(outcome);
+        }
+        report
+    }
+
+    /// Save file of one post
+    async fn save(&self, post: Post) -> Outcome {
+        let url = self.url(&post).to_string();
+        let path = self.path(&post);
+        let existing = match self.skip_existing {
+            true => fs::metadata(&path).await.ok(),
+            false => None,
+        };
+        let verify = self.verify && url == post.file_url && is_md5(&post.hash);
+        let expected = verify.then_some(post.hash.as_str());
+        let result =
+            match existing {
+                Some(metadata) => match self.check_existing(&post, &path, expected).await {
+                    // sidecars of old files are added if they're missing
+                    Ok(verified) => self.add_sidecars(&post, &url, &path).await.map(|sidecars| {
+                        Outcome::Skipped(Saved {
+                            id: post.id,
+                            url: url.clone(),
+                            path: path.clone(),
+                            bytes: metadata.len(),
+                            verified,
+                            sidecars,
+                        })
+                    }),
+                    Err(error) => Err(error),
+                },
+                None => self.fetch(&post, &url, &path, expected).await.map(
+                    |(bytes, verified, sidecars)| {
+                        Outcome::Saved(Saved {
+                            id: post.id,
+                            url: url.clone(),
+                            path: path.clone(),
+                            bytes,
+                            verified,
+                            sidecars,
+                        })
+                    },
+                ),
+            };
+        match result {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                tracing::warn!(id = post.id, url = %url, error = %error, "file isn't saved");
+                Outcome::Failed(Failed {
+                    id: post.id,
+                    url,
+                    path,
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Check that existing file at `path` is file of `post`, not of other post with the same path
+    ///
+    /// File is checked by id in [Sidecar::Json] and by md5 if it's `expected`,
+    /// file with other md5 is accepted only by sidecar. Returns if md5 is `expected`
+    async fn check_existing(
+        &self,
+        post: &Post,
+        path: &Path,
+        expected: Option<&str>,
+    ) -> Result<Option<bool>> {
+        let taken = |owner: String| {
+            Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("existing file belongs to {}", owner),
+            ))
+        };
+        let owner = match fs::read(Sidecar::Json.path(path)).await {
+            Ok(json) => serde_json::from_slice::<Post>(&json).ok().map(|x| x.id),
+            Err(_) => None,
+        };
+        if let Some(owner) = owner.filter(|x| *x != post.id) {
+            return Err(taken(format!("post {}", owner)));
+        }
+        let Some(expected) = expected else {
+            return Ok(None);
+        };
+        let mut md5 = Md5::new();
+        md5.update_file(path).await?;
+        let actual = md5.hex();
+        match actual.eq_ignore_ascii_case(expected) {
+            true => Ok(Some(true)),
+            false if owner.is_some() => Ok(Some(false)),
+            false => Err(taken(format!("other file with md5 {}", actual))),
+        }
+    }
+
+    /// Download `url` into `path` through `.part` file, returns size of file,
+    /// if its md5 is `expected` and paths of sidecars
+    async fn fetch(
+        &self,
+        post: &Post,
+        url: &str,
+        path: &Path,
+        expected: Option<&str>,
+    ) -> Result<(u64, Option<bool>, Vec<PathBuf>)> {
+        let url = Url::parse(url).map_err(|error| Error::InvalidUrl(url.to_string(), error))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let part = part_path(path);
+        if !self.resume {
+            discard(&part).await?;
+        }
+        let (url, part) = (&url, part.as_path());
+        let result = self
+            .retry
+            .run(url, || async move {
+                let (bytes, actual) = self.write_part(url, part, expected.is_some()).await?;
+                let mismatch = match (expected, actual) {
+                    (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(&actual) => {
+                        Error::Checksum {
+                            expected: expected.to_ascii_lowercase(),
+                            actual,
+                        }
+                    }
+                    _ => return Ok((bytes, None)),
+                };
+                match self.on_mismatch {
+                    OnMismatch::Retry => {
+                        discard(part).await?;
+                        Err(mismatch)
+                    }
+                    _ => Ok((bytes, Some(mismatch))),
+                }
+            })
+            .await;
+        match result {
+            Ok((bytes, Some(mismatch))) if self.on_mismatch == OnMismatch::Quarantine => {
+                let quarantine = quarantine_path(path);
+                if let Some(dir) = quarantine.parent() {
+                    fs::create_dir_all(dir).await?;
+                }
+                fs::rename(part, &quarantine).await?;
+                PartMeta::remove(part).await?;
+                tracing::warn!(
+                    bytes,
+                    path = %quarantine.display(),
+                    error = %mismatch,
+                    "file is moved into quarantine"
+                );
+                Err(mismatch)
+            }
+            Ok((bytes, mismatch)) => {
+                let sidecars = self.write_sidecars(post, url.as_str(), path).await?;
+                if let Err(error) = fs::rename(part, path).await {
+                    for sidecar in &sidecars {
+                        let _ = fs::remove_file(part_path(sidecar)).await;
+                    }
+                    return Err(error.into());
+                }
+                PartMeta::remove(part).await?;
+                let sidecars = commit_sidecars(sidecars).await?;
+                if let Some(mismatch) = &mismatch {
+                    tracing::warn!(path = %path.display(), error = %mismatch, "file is kept");
+                }
+                Ok((bytes, expected.map(|_| mismatch.is_none()), sidecars))
+            }
+            Err(error) => {
+                if !self.resume {
+                    if let Err(error) = discard(part).await {
+                        tracing::warn!(path = %part.display(), error = %error, "part isn't removed");
+                    }
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Write [Downloader::sidecar] files of `post` into `.part` files, returns their paths
+    ///
+    /// They're renamed by [commit_sidecars] right after file at `path`
+    async fn write_sidecars(&self, post: &Post, url: &str, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut written = vec![];
+        for format in &self.sidecars {
+            let sidecar = format.path(path);
+            match write_synced(&part_path(&sidecar), &format.render(post, url)).await {
+                Ok(()) => written.push(sidecar),
+                Err(error) => {
+                    for sidecar in written {
+                        let _ = fs::remove_file(part_path(&sidecar)).await;
+                    }
+                    return Err(error.into());
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    /// Write sidecars of existing file at `path` which are missing, returns paths of all sidecars
+    async fn add_sidecars(&self, post: &Post, url: &str, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut sidecars = vec![];
+        for format in &self.sidecars {
+            let sidecar = format.path(path);
+            if fs::metadata(&sidecar).await.is_err() {
+                let part = part_path(&sidecar);
+                write_synced(&part, &format.render(post, url)).await?;
+                fs::rename(&part, &sidecar).await?;
+            }
+            sidecars.push(sidecar);
+        }
+        Ok(sidecars)
+    }
+
+    /// Write body of `url` into `part`, returns size of file and its md5 if `verify` is on
+    ///
+    /// Existing `part` is continued by `Range` request, it's restarted if remote file changed
+    async fn write_part(
+        &self,
+        url: &Url,
+        part: &Path,
+        verify: bool,
+    ) -> Result<(u64, Option<String>)> {
+        loop {
+            let mut md5 = verify.then(Md5::new);
+            let mut offset = match self.resume {
+                true => fs::metadata(part).await.map(|x| x.len()).unwrap_or(0),
+                false => 0,
+            };
+            let meta = match offset {
+                0 => None,
+                _ => PartMeta::load(part).await.filter(PartMeta::is_resumable),
+            };
+            if offset > 0 && meta.is_none() {
+                // nothing tells if remote file is the same
+                tracing::debug!(url = %redact_url(url), "part can't be checked, restarting");
+                discard(part).await?;
+                offset = 0;
+            }
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire(url).await;
+            }
+            let mut request = Request::get(url.clone());
+            if offset > 0 {
+                request = request.header(header::RANGE.as_str(), &format!("bytes={}-", offset));
+                if let Some(validator) = meta.as_ref().and_then(PartMeta::validator) {
+                    request = request.header(header::IF_RANGE.as_str(), validator);
+                }
+            }
+            let response = self.transport.send(request).await?;
+            if offset > 0 && response.status == StatusCode::RANGE_NOT_SATISFIABLE {
+                let total = ContentRange::of(&response).and_then(|x| x.total);
+                let length = meta.as_ref().and_then(|x| x.length);
+                // `part` already has whole file
+                if total == Some(offset) && length.is_none_or(|x| x == offset) {
+                    if let Some(md5) = &mut md5 {
+                        md5.update_file(part).await?;
+                    }
+                    return Ok((offset, md5.map(Md5::hex)));
+                }
+                tracing::debug!(url = %redact_url(url), "part isn't satisfiable, restarting");
+                discard(part).await?;
+                continue;
+            }
+            let mut response = response.error_for_status().inspect_err(|error| {
+                if let (Some(limiter), Some(wait)) =
+                    (&self.rate_limiter, self.retry.retry_after(error))
+                {
+                    limiter.pause(url, wait);
+                }
+            })?;
+            let range = ContentRange::of(&response);
+            let (mut file, start, total) = match (response.status, range) {
+                (StatusCode::PARTIAL_CONTENT, Some(range)) if offset > 0 => {
+                    let meta = meta.expect("part is resumed only with validators");
+                    if range.start != Some(offset) || !meta.matches(&response, &range) {
+                        tracing::debug!(url = %redact_url(url), "remote file changed, restarting");
+                        discard(part).await?;
+                        continue;
+                    }
+                    if let Some(md5) = &mut md5 {
+                        md5.update_file(part).await?;
+                    }
+                    let file = fs::OpenOptions::new().append(true).open(part).await?;
+                    (file, offset, range.total.or(meta.length))
+                }
+                (StatusCode::PARTIAL_CONTENT, _) => {
+                    return Err(Error::Status(StatusCode::PARTIAL_CONTENT));
+                }
+                // whole file
+                _ => {
+                    let meta = PartMeta::of(&response);
+                    let file = fs::File::create(part).await?;
+                    if self.resume {
+                        meta.save(part).await?;
+                    }
+                    (file, 0, meta.length)
+                }
+            };
+            let mut bytes = start;
+            while let Some(chunk) = response.body.chunk().await? {
+                file.write_all(&chunk).await?;
+                if let Some(md5) = &mut md5 {
+                    md5.update(&chunk);
+                }
+                bytes += chunk.len() as u64;
+            }
+            file.sync_all().await?;
+            return match total {
+                Some(total) if bytes != total => Err(Error::transport(format!(
+                    "file has {} bytes, but {} were expected",
+                    bytes, total
+                ))),
+                _ => Ok((bytes, md5.map(Md5::hex))),
+            };
+        }
+    }
+}
+
+/// Remove unfinished file and its validators, missing files aren't errors
+async fn discard(part: &Path) -> std::io::Result<()> {
+    match fs::remove_file(part).await {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    PartMeta::remove(part).await
+}
+
+/// Write `data` into `path` and flush it to disk, like media file before rename
+async fn write_synced(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path).await?;
+    file.write_all(data).await?;
+    file.sync_all().await
+}
+
+/// Rename `.part` files of sidecars written by [Downloader::write_sidecars]
+async fn commit_sidecars(sidecars: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    for sidecar in &sidecars {
+        fs::rename(part_path(sidecar), sidecar).await?;
+    }
+    Ok(sidecars)
+}
+
+/// Path of unfinished file, like `dir/123.png.part`
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(PART);
+    path.with_file_name(name)
+}
+
+/// Path of file with wrong md5, like `dir/.quarantine/123.png`
+fn quarantine_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    match path.parent() {
+        Some(dir) => dir.join(QUARANTINE).join(name),
+        None => Path::new(QUARANTINE).join(name),
+    }
+}
+
+/// Wait for next finished task, panic of task is resumed
+async fn join_next(tasks: &mut JoinSet<(usize, Outcome)>) -> Option<(usize, Outcome)> {
+    match tasks.join_next().await? {
+        Ok(outcome) => Some(outcome),
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+/// Result of one post
+#[derive(Debug)]
+enum Outcome {
+    Saved(Saved),
+    Skipped(Saved),
+    Failed(Failed),
+}
+
+/// File which is on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saved {
+    /// Id of post
+    pub id: i64,
+    /// Url of saved [Rendition]
+    pub url: String,
+    /// Path of file, inside [Downloader::dir]
+    pub path: PathBuf,
+    /// Size of file
+    pub bytes: u64,
+    /// If md5 of file is `hash` of post, [None] if it wasn't checked
+    pub verified: Option<bool>,
+    /// Paths of [Downloader::sidecar] files
+    pub sidecars: Vec<PathBuf>,
+}
+
+/// File which isn't saved
+#[derive(Debug)]
+pub struct Failed {
+    /// Id of post
+    pub id: i64,
+    /// Url of chosen [Rendition]
+    pub url: String,
+    /// Path where file would be saved
+    pub path: PathBuf,
+    /// Error of the last attempt
+    pub error: Error,
+}
+
+/// Summary of [Downloader], entries are in order of posts
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Downloaded files
+    pub saved: Vec<Saved>,
+    /// Files which already existed
+    pub skipped: Vec<Saved>,
+    /// Files which aren't saved
+    pub failed: Vec<Failed>,
+    /// Error of page which stopped [Downloader::download_pages]
+    pub page_error: Option<Error>,
+}
+
+impl Report {
+    /// Check if nothing failed
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.page_error.is_none()
+    }
+
+    /// Number of all posts
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.saved.len() + self.skipped.len() + self.failed.len()
+    }
+
+    /// Check if there are no posts
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Downloaded bytes, without skipped files
+    pub fn bytes(&self) -> u64 {
+        self.saved.iter().map(|x| x.bytes).sum()
+    }
+
+    /// Append entries of `other`
+    pub fn merge(&mut self, other: Report) {
+        self.saved.extend(other.saved);
+        self.skipped.extend(other.skipped);
+        self.failed.extend(other.failed);
+        self.page_error = self.page_error.take().or(other.page_error);
+    }
+
+    fn push(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Saved(x) => self.saved.push(x),
+            Outcome::Skipped(x) => self.skipped.push(x),
+            Outcome::Failed(x) => self.failed.push(x),
+        }
+    }
+}
+
+/// Render summary like `saved 3 (1024 bytes), skipped 1, failed 0`,
+/// with `, page error: ...` if pages were stopped
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "saved {} ({} bytes), skipped {}, failed {}",
+            self.saved.len(),
+            self.bytes(),
+            self.skipped.len(),
+            self.failed.len()
+        )?;
+        match &self.page_error {
+            Some(error) => write!(f, ", page error: {}", error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use bytes::Bytes;
+
+    use crate::prelude::*;
+    use crate::temp_dir::TempDir;
+    use crate::testing::FakeBooru;
+    use crate::transport::{Body, BodyStream, Response};
+
+    /// Files by url, counts requests which are sent at once
+    #[derive(Debug, Default)]
+    struct Files {
+        files: Mutex<HashMap<String, &'static str>>,
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+        sent: AtomicUsize,
+    }
+
+    impl Files {
+        fn with(files: &[(&str, &'static str)]) -> Arc<Self> {
+            let this = Self::default();
+            for (url, body) in files {
+                this.files.lock().unwrap().insert(url.to_string(), body);
+            }
+            Arc::new(this)
+        }
+
+        fn requests(&self) -> usize {
+            self.sent.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl Transport for Arc<Files> {
+        async fn send(&self, request: Request) -> Result<Response> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            let body = self
+                .files
+                .lock()
+                .unwrap()
+                .get(request.url.as_str())
+                .copied();
+            Ok(match body {
+                Some(body) => Response::new(StatusCode::OK, body),
+                None => Response::new(StatusCode::NOT_FOUND, ""),
+            })
+        }
+    }
+
+    fn post(id: i64, file_url: &str) -> Post {
+        Post {
+            id,
+            file_url: file_url.to_string(),
+            sample_url: file_url.replace("/images/", "/samples/"),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn download() {
+        let dir = TempDir::new("download");
+        let files = Files::with(&[
+            ("https://cdn/images/1.png", "one"),
+            ("https://cdn/images/2.mp4", "two"),
+            ("https://cdn/samples/3.jpg", "three"),
+            ("https://cdn/images/5.gif", "five"),
+        ]);
+        let posts: Posts = vec![
+            post(1, "https://cdn/images/1.png"),
+            post(2, "https://cdn/images/2.mp4"),
+            post(3, "https://cdn/images/3.jpg"),
+            post(4, "https://cdn/images/4.png"),
+            post(5, "https://cdn/images/5.gif"),
+        ]
+        .into();
+        let downloader = Downloader::new(&*dir)
+            .transport(files.clone())
+            .retry(RetryPolicy::none())
+            .concurrency(2)
+            .rendition_with(|post| match post.id {
+                3 => Rendition::Sample,
+                _ => Rendition::File,
+            });
+        let report = downloader.download(&posts).await;
+        assert_eq!(
+            report.saved.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![1, 2, 3, 5]
+        );
+        assert_eq!(report.failed.len(), 1);
+        assert!(matches!(
+            report.failed[0].error,
+            Error::Status(StatusCode::NOT_FOUND)
+        ));
+        assert_eq!(report.bytes(), 15);
+        assert_eq!(files.max_active.load(Ordering::SeqCst), 2);
+        assert_eq!(std::fs::read_to_string(dir.join("3.jpg")).unwrap(), "three");
+        assert!(!dir.join("4.png").exists());
+        assert!(!dir.join("4.png.part").exists());
+
+        let report = downloader.download(&posts).await;
+        assert_eq!(report.skipped.len(), 4);
+        assert_eq!(report.to_string(), "saved 0 (0 bytes), skipped 4, failed 1");
+
+        let report = downloader
+            .skip_existing(false)
+            .download_mini(&posts.get_urls_ext())
+            .await;
+        assert_eq!(report.saved.len(), 4);
+    }
+
+    /// Body which breaks after first chunk
+    struct Broken(Option<Bytes>);
+
+    #[async_trait]
+    impl BodyStream for Broken {
+        async fn chunk(&mut self) -> Result<Option<Bytes>> {
+            match self.0.take() {
+                Some(chunk) => Ok(Some(chunk)),
+                None => Err(Error::transport("connection reset")),
+            }
+        }
+    }
+
+    /// One file which supports `Range` and `If-Range`
+    #[derive(Debug)]
+    struct Ranged {
+        body: &'static str,
+        etag: &'static str,
+        /// First answer is broken after this number of bytes
+        break_at: Mutex<Option<usize>>,
+        requests: Mutex<Vec<Request>>,
+    }
+
+    impl Ranged {
+        fn new(body: &'static str, etag: &'static str, break_at: Option<usize>) -> Arc<Self> {
+            Arc::new(Self {
+                body,
+                etag,
+                break_at: Mutex::new(break_at),
+                requests: Default::default(),
+            })
+        }
+
+        fn header(&self, index: usize, name: &header::HeaderName) -> Option<String> {
+            let requests = self.requests.lock().unwrap();
+            let value = requests.get(index)?.headers.get(name)?;
+            Some(value.to_str().ok()?.to_string())
+        }
+    }
+
+    #[async_trait]
+    impl Transport for Arc<Ranged> {
+        async fn send(&self, request: Request) -> Result<Response> {
+            self.requests.lock().unwrap().push(request.clone());
+            let len = self.body.len();
+            let header = |name: &header::HeaderName| {
+                request
+                    .headers
+                    .get(name)
+                    .and_then(|x| x.to_str().ok())
+                    .map(String::from)
+            };
+            let start = header(&header::RANGE)
+                .filter(|_| header(&header::IF_RANGE).is_none_or(|x| x == self.etag))
+                .and_then(|x| x.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok());
+            let response = match start {
+                Some(start) if start >= len => {
+                    return Ok(Response::new(StatusCode::RANGE_NOT_SATISFIABLE, "")
+                        .header("Content-Range", &format!("bytes */{}", len)));
+                }
+                Some(start) => Response::new(StatusCode::PARTIAL_CONTENT, &self.body[start..])
+                    .header(
+                        "Content-Range",
+                        &format!("bytes {}-{}/{}", start, len - 1, len),
+                    ),
+                None => Response::new(StatusCode::OK, self.body)
+                    .header("Content-Length", &len.to_string()),
+            };
+            let mut response = response.header("ETag", self.etag);
+            if let Some(at) = self.break_at.lock().unwrap().take() {
+                let chunk = Bytes::from(&self.body[..at]);
+                response.body = Body::Stream(Box::new(Broken(Some(chunk))));
+            }
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn resume() {
+        let dir = TempDir::new("resume");
+        let posts: Posts = vec![Post {
+            hash: "781e5e245d69b566979b86e28d23f2c7".to_string(),
+            ..post(7, "https://cdn/images/7.png")
+        }]
+        .into();
+        let path = dir.join("7.png");
+        let part = part_path(&path);
+        let retry = RetryPolicy::init()
+            .max_attempts(2)
+            .base_delay(Duration::from_millis(1));
+
+        // broken answer is continued
+        let file = Ranged::new("0123456789", "\"v1\"", Some(4));
+        let downloader = Downloader::new(&*dir)
+            .transport(file.clone())
+            .retry(retry.clone());
+        let report = downloader.download(&posts).await;
+        assert_eq!(report.saved[0].bytes, 10);
+        // md5 includes bytes of part
+        assert_eq!(report.saved[0].verified, Some(true));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(file.header(1, &header::RANGE).as_deref(), Some("bytes=4-"));
+        assert_eq!(file.header(1, &header::IF_RANGE).as_deref(), Some("\"v1\""));
+        assert!(std::fs::read_dir(&*dir).unwrap().count() == 1);
+
+        // remote file changed
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&part, "abc").unwrap();
+        PartMeta {
+            etag: Some("\"old\"".to_string()),
+            ..Default::default()
+        }
+        .save(&part)
+        .await
+        .unwrap();
+        let file = Ranged::new("0123456789", "\"v2\"", None);
+        let report = downloader
+            .clone()
+            .transport(file.clone())
+            .download(&posts)
+            .await;
+        assert!(report.is_success());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(file.requests.lock().unwrap().len(), 1);
+
+        // part is already complete
+        std::fs::rename(&path, &part).unwrap();
+        PartMeta {
+            length: Some(10),
+            ..Default::default()
+        }
+        .save(&part)
+        .await
+        .unwrap();
+        let file = Ranged::new("0123456789", "\"v2\"", None);
+        let report = downloader
+            .clone()
+            .transport(file.clone())
+            .download(&posts)
+            .await;
+        assert_eq!(report.saved[0].bytes, 10);
+        assert_eq!(report.saved[0].verified, Some(true));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(file.header(0, &header::RANGE).as_deref(), Some("bytes=10-"));
+
+        // part without validators is restarted
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&part, "abc").unwrap();
+        let file = Ranged::new("0123456789", "\"v2\"", None);
+        let report = downloader
+            .clone()
+            .transport(file.clone())
+            .download(&posts)
+            .await;
+        assert!(report.is_success());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(file.header(0, &header::RANGE), None);
+
+        // part is removed without resume
+        std::fs::remove_file(&path).unwrap();
+        let file = Ranged::new("0123456789", "\"v1\"", Some(4));
+        let report = downloader
+            .transport(file)
+            .retry(RetryPolicy::none())
+            .resume(false)
+            .download(&posts)
+            .await;
+        assert_eq!(report.failed.len(), 1);
+        assert!(std::fs::read_dir(&*dir).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn verify() {
+        let dir = TempDir::new("verify");
+        let files = Files::with(&[
+            ("https://cdn/images/1.png", "one"),
+            ("https://cdn/images/2.png", "two"),
+        ]);
+        let posts: Posts = vec![
+            Post {
+                hash: "F97C5D29941BFB1B2FDAB0874906AB82".to_string(),
+                ..post(1, "https://cdn/images/1.png")
+            },
+            Post {
+                // md5 of `one`
+                hash: "f97c5d29941bfb1b2fdab0874906ab82".to_string(),
+                ..post(2, "https://cdn/images/2.png")
+            },
+        ]
+        .into();
+        let downloader = Downloader::new(&*dir).transport(files.clone()).retry(
+            RetryPolicy::init()
+                .max_attempts(2)
+                .base_delay(Duration::from_millis(1)),
+        );
+
+        let report = downloader.download(&posts).await;
+        assert_eq!(report.saved[0].verified, Some(true));
+        assert!(matches!(
+            &report.failed[0].error,
+            Error::Checksum { actual, .. } if actual == "b8a9f715dbb64fd5c56e7783c6820a61"
+        ));
+        // first file once, second one twice
+        assert_eq!(files.requests(), 3);
+        assert!(!dir.join("2.png").exists());
+
+        let report = downloader
+            .clone()
+            .on_mismatch(OnMismatch::Quarantine)
+            .download(&posts)
+            .await;
+        assert_eq!(report.skipped.len(), 1);
+        assert!(matches!(report.failed[0].error, Error::Checksum { .. }));
+        assert_eq!(
+            std::fs::read_to_string(dir.join(".quarantine/2.png")).unwrap(),
+            "two"
+        );
+
+        let report = downloader
+            .clone()
+            .on_mismatch(OnMismatch::Keep)
+            .download(&posts)
+            .await;
+        assert_eq!(report.saved[0].verified, Some(false));
+        assert!(dir.join("2.png").exists());
+
+        let report = downloader
+            .skip_existing(false)
+            .verify(false)
+            .download(&posts)
+            .await;
+        assert!(report.saved.iter().all(|x| x.verified.is_none()));
+    }
+
+    #[tokio::test]
+    async fn template() {
+        let dir = TempDir::new("template");
+        let files = Files::with(&[
+            ("https://cdn/images/1.png", "one"),
+            ("https://cdn/images/2.png", "two"),
+            ("https://cdn/images/3.png", "three"),
+        ]);
+        let posts: Posts = vec![
+            Post {
+                owner: "molly".to_string(),
+                ..post(1, "https://cdn/images/1.png")
+            },
+            Post {
+                owner: "molly".to_string(),
+                ..post(2, "https://cdn/images/2.png")
+            },
+            post(3, "https://cdn/images/3.png"),
+        ]
+        .into();
+        let template: Template = "{uploader}/{owner}.{ext}".parse().unwrap();
+        assert_eq!(
+            template.collisions(posts.iter(), |x| &x.file_url),
+            vec![Collision {
+                path: PathBuf::from("molly/molly.png"),
+                first: 1,
+                second: 2,
+            }]
+        );
+        let report = Downloader::new(&*dir)
+            .transport(files.clone())
+            .retry(RetryPolicy::none())
+            .template(template)
+            .download(&posts)
+            .await;
+        assert_eq!(
+            report.saved.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(report.failed[0].id, 2);
+        assert!(
+            matches!(&report.failed[0].error, Error::Io(x) if x.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(files.requests(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("molly/molly.png")).unwrap(),
+            "one"
+        );
+        assert!(dir.join("unknown/unknown.png").exists());
+    }
+
+    /// Fake api which fails after the first request
+    #[derive(Debug)]
+    struct FirstPage(FakeBooru, AtomicUsize);
+
+    #[async_trait]
+    impl Transport for FirstPage {
+        async fn send(&self, request: Request) -> Result<Response> {
+            match self.1.fetch_add(1, Ordering::SeqCst) {
+                0 => self.0.send(request).await,
+                _ => Ok(Response::new(StatusCode::SERVICE_UNAVAILABLE, "")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn pages() {
+        let dir = TempDir::new("pages");
+        let files = Files::with(&[
+            ("https://fake.booru/images/4.png", "four"),
+            ("https://fake.booru/images/3.png", "three"),
+        ]);
+        let client = R34Client::init()
+            .transport(FirstPage(
+                FakeBooru::init().generate(4),
+                AtomicUsize::new(0),
+            ))
+            .retry(RetryPolicy::none());
+        let report = Downloader::new(&*dir)
+            .transport(files)
+            .retry(RetryPolicy::none())
+            .verify(false)
+            .download_pages(client.pages(&R34Params::init().limit(2).page(0)))
+            .await;
+        let ids: Vec<i64> = report.saved.iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![4, 3]);
+        assert!(matches!(
+            report.page_error,
+            Some(Error::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        assert!(!report.is_success());
+        assert!(report
+            .to_string()
+            .ends_with("page error: unexpected status: 503 Service Unavailable"));
+    }
+
+    #[tokio::test]
+    async fn artist() {
+        let dir = TempDir::new("artist");
+        let booru = FakeBooru::init()
+            .generate(3)
+            .tag_type("shark", TagType::Artist);
+        let files = Files::with(&[
+            ("https://fake.booru/images/1.png", "one"),
+            ("https://fake.booru/images/2.png", "two"),
+            ("https://fake.booru/images/3.png", "three"),
+        ]);
+        let report = Downloader::new(&*dir)
+            .transport(files)
+            .retry(RetryPolicy::none())
+            .verify(false)
+            .template("{artist}/{id}.{ext}".parse().unwrap())
+            .download_pages(booru.client().pages(&R34Params::init().limit(5).page(0)))
+            .await;
+        assert!(report.is_success(), "{:?}", report);
+        for saved in &report.saved {
+            let post = FakeBooru::fake_post(saved.id as u64);
+            let artist = match post.tags.split_whitespace().any(|x| x == "shark") {
+                true => "shark",
+                false => "unknown",
+            };
+            assert_eq!(
+                saved.path,
+                dir.join(artist).join(format!("{}.png", post.id))
+            );
+        }
+        assert!(report
+            .saved
+            .iter()
+            .any(|x| x.path.starts_with(dir.join("shark"))));
+        // one request of posts and one of tag types
+        assert_eq!(booru.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn existing() {
+        let dir = TempDir::new("existing");
+        let files = Files::with(&[
+            ("https://cdn/images/1.png", "one"),
+            ("https://cdn/images/2.png", "two"),
+        ]);
+        let post = |id, url| Post {
+            owner: "molly".to_string(),
+            ..post(id, url)
+        };
+        let first = post(1, "https://cdn/images/1.png");
+        let second = Post {
+            // md5 of `two`
+            hash: "b8a9f715dbb64fd5c56e7783c6820a61".to_string(),
+            ..post(2, "https://cdn/images/2.png")
+        };
+        let downloader = Downloader::new(&*dir)
+            .transport(files.clone())
+            .retry(RetryPolicy::none())
+            .template("{owner}.{ext}".parse().unwrap());
+
+        // pages share paths
+        let mut paths = HashMap::new();
+        let report = downloader.download_with([first.clone()], &mut paths).await;
+        assert_eq!(report.saved.len(), 1);
+        let report = downloader.download_with([second.clone()], &mut paths).await;
+        assert_eq!(report.failed[0].id, 2);
+        assert_eq!(files.requests(), 1);
+
+        // file of other post isn't skipped, it's checked by md5
+        let report = downloader.download_iter([second.clone()]).await;
+        assert!(report.failed[0]
+            .error
+            .to_string()
+            .contains("other file with md5"));
+        let report = downloader
+            .clone()
+            .verify(false)
+            .download_iter([second.clone()])
+            .await;
+        assert_eq!(report.skipped.len(), 1);
+
+        // or by sidecar
+        std::fs::remove_file(dir.join("molly.png")).unwrap();
+        let downloader = downloader.verify(false).sidecar(Sidecar::Json);
+        downloader.download_iter([first.clone()]).await;
+        let report = downloader.download_iter([second]).await;
+        assert!(report.failed[0].error.to_string().contains("post 1"));
+        let report = downloader.download_iter([first]).await;
+        assert_eq!(report.skipped.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sidecars() {
+        let dir = TempDir::new("sidecars");
+        let files = Files::with(&[("https://cdn/images/1.png", "one")]);
+        let posts: Posts = vec![
+            Post {
+                tags: "dark fish".to_string(),
+                ..post(1, "https://cdn/images/1.png")
+            },
+            post(2, "https://cdn/images/2.png"),
+        ]
+        .into();
+        let downloader = Downloader::new(&*dir)
+            .transport(files.clone())
+            .retry(RetryPolicy::none())
+            .sidecar(Sidecar::Txt)
+            .sidecar(Sidecar::Json)
+            .sidecar(Sidecar::Txt);
+
+        let report = downloader.download(&posts).await;
+        assert_eq!(
+            report.saved[0].sidecars,
+            vec![dir.join("1.png.txt"), dir.join("1.png.json")]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("1.png.txt")).unwrap(),
+            "dark\nfish\n"
+        );
+        let json = std::fs::read(dir.join("1.png.json")).unwrap();
+        assert_eq!(serde_json::from_slice::<Post>(&json).unwrap(), posts[0]);
+        // failed file has no sidecars
+        let mut names: Vec<_> = std::fs::read_dir(&*dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["1.png", "1.png.json", "1.png.txt"]);
+
+        std::fs::remove_file(dir.join("1.png.txt")).unwrap();
+        let report = downloader.sidecar(Sidecar::Xmp).download(&posts).await;
+        assert_eq!(report.skipped[0].sidecars.len(), 3);
+        assert!(dir.join("1.png.txt").exists());
+        assert!(dir.join("1.png.xmp").exists());
+        assert_eq!(files.requests(), 3);
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::transport::{header, Response};
+
+/// Validators of remote file, stored next to `.part` file
+///
+/// They're sent in `If-Range`, so server answers with whole file if it changed
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PartMeta {
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+    /// Size of whole file
+    pub(crate) length: Option<u64>,
+}
+
+impl PartMeta {
+    /// Validators of `response` with whole file
+    pub(crate) fn of(response: &Response) -> Self {
+        let header =
+            |name: &header::HeaderName| response.header_str(name.as_str()).map(String::from);
+        Self {
+            etag: header(&header::ETAG),
+            last_modified: header(&header::LAST_MODIFIED),
+            length: header(&header::CONTENT_LENGTH).and_then(|x| x.trim().parse().ok()),
+        }
+    }
+
+    /// Value of `If-Range`, strong `ETag` is preferred
+    pub(crate) fn validator(&self) -> Option<&str> {
+        match &self.etag {
+            Some(etag) if !etag.starts_with("W/") => Some(etag),
+            _ => self.last_modified.as_deref(),
+        }
+    }
+
+    /// Strong `ETag`, which is sent in `If-Range`
+    fn strong_etag(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|x| !x.starts_with("W/"))
+    }
+
+    /// Check if part can be continued, it needs strong `ETag` or size of file
+    pub(crate) fn is_resumable(&self) -> bool {
+        self.strong_etag().is_some() || self.length.is_some()
+    }
+
+    /// Check if `response` with part of file is part of the same file
+    ///
+    /// Stored size must be equal to size in `Content-Range`, stored `ETag` is checked by
+    /// server through `If-Range` and also here if response has it
+    pub(crate) fn matches(&self, response: &Response, range: &ContentRange) -> bool {
+        let etag = response.header_str(header::ETAG.as_str());
+        let same_etag = match (self.strong_etag(), etag) {
+            (Some(stored), Some(etag)) => stored == etag,
+            _ => true,
+        };
+        let same_length = match (self.length, range.total) {
+            (Some(stored), Some(total)) => stored == total,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        self.is_resumable() && same_etag && same_length
+    }
+
+    /// Read validators of `part`, [None] if they're missing or broken
+    pub(crate) async fn load(part: &Path) -> Option<Self> {
+        let json = fs::read(meta_path(part)).await.ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+
+    pub(crate) async fn save(&self, part: &Path) -> std::io::Result<()> {
+        fs::write(meta_path(part), serde_json::to_vec(self)?).await
+    }
+
+    /// Remove validators of `part`, missing validators aren't error
+    pub(crate) async fn remove(part: &Path) -> std::io::Result<()> {
+        match fs::remove_file(meta_path(part)).await {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Path of validators, like `dir/123.png.part.meta`
+fn meta_path(part: &Path) -> PathBuf {
+    let mut name = part.file_name().unwrap_or_default().to_os_string();
+    name.push(".meta");
+    part.with_file_name(name)
+}
+
+/// `Content-Range` header like `bytes 100-199/1000` or `bytes */1000`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ContentRange {
+    /// First byte, [None] for `*`
+    pub(crate) start: Option<u64>,
+    /// Size of whole file, [None] for `*`
+    pub(crate) total: Option<u64>,
+}
+
+impl ContentRange {
+    pub(crate) fn of(response: &Response) -> Option<Self> {
+        Self::parse(response.header_str(header::CONTENT_RANGE.as_str())?)
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+        let total = match total {
+            "*" => None,
+            x => Some(x.parse().ok()?),
+        };
+        let start = match range {
+            "*" => None,
+            x => {
+                let (start, end) = x.split_once('-')?;
+                let start: u64 = start.parse().ok()?;
+                if end.parse::<u64>().ok()? < start {
+                    return None;
+                }
+                Some(start)
+            }
+        };
+        Some(Self { start, total })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_range() {
+        assert_eq!(
+            ContentRange::parse("bytes 100-199/1000"),
+            Some(ContentRange {
+                start: Some(100),
+                total: Some(1000)
+            })
+        );
+        assert_eq!(
+            ContentRange::parse("bytes */1000"),
+            Some(ContentRange {
+                start: None,
+                total: Some(1000)
+            })
+        );
+        assert_eq!(
+            ContentRange::parse("bytes 0-9/*").and_then(|x| x.total),
+            None
+        );
+        assert_eq!(ContentRange::parse("bytes 9-0/10"), None);
+        assert_eq!(ContentRange::parse("items 0-9/10"), None);
+    }
+
+    #[test]
+    fn validator() {
+        let meta = PartMeta {
+            etag: Some("W/\"weak\"".to_string()),
+            last_modified: Some("Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            length: None,
+        };
+        assert_eq!(meta.validator(), meta.last_modified.as_deref());
+        let meta = PartMeta {
+            etag: Some("\"strong\"".to_string()),
+            ..meta
+        };
+        assert_eq!(meta.validator(), Some("\"strong\""));
+        assert!(meta.is_resumable());
+    }
+
+    #[test]
+    fn matches() {
+        let range = |total| ContentRange {
+            start: Some(5),
+            total,
+        };
+        let response = Response::new(crate::transport::StatusCode::PARTIAL_CONTENT, "");
+        // nothing to check
+        let meta = PartMeta {
+            etag: Some("W/\"weak\"".to_string()),
+            ..Default::default()
+        };
+        assert!(!meta.is_resumable());
+        assert!(!meta.matches(&response, &range(Some(10))));
+
+        let meta = PartMeta {
+            length: Some(10),
+            ..Default::default()
+        };
+        assert!(meta.matches(&response, &range(Some(10))));
+        assert!(!meta.matches(&response, &range(Some(11))));
+        assert!(!meta.matches(&response, &range(None)));
+
+        let meta = PartMeta {
+            etag: Some("\"v1\"".to_string()),
+            ..Default::default()
+        };
+        let response = |etag| {
+            Response::new(crate::transport::StatusCode::PARTIAL_CONTENT, "").header("ETag", etag)
+        };
+        assert!(meta.matches(&response("\"v1\""), &range(None)));
+        assert!(!meta.matches(&response("\"v2\""), &range(None)));
+    }
+}
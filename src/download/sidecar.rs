@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use crate::rules::rule34::data::Post;
+
+/// Metadata file next to saved file, like `123.png.json`
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::download::Sidecar;
+///
+/// let post = Post { tags: "dark fish".to_string(), ..Default::default() };
+/// assert_eq!(Sidecar::Txt.render(&post, "https://cdn/images/1.png"), b"dark\nfish\n");
+/// assert_eq!(
+///     Sidecar::Xmp.path("media/1.png".as_ref()),
+///     std::path::PathBuf::from("media/1.png.xmp")
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sidecar {
+    /// Full [Post] as json
+    Json,
+    /// One tag per line, like sidecars of Hydrus
+    Txt,
+    /// XMP packet with tags in `dc:subject`, read by most photo managers
+    Xmp,
+}
+
+impl Sidecar {
+    /// Extension added to name of saved file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Txt => "txt",
+            Self::Xmp => "xmp",
+        }
+    }
+
+    /// Path of sidecar of file at `path`
+    pub fn path(&self, path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(self.extension());
+        path.with_file_name(name)
+    }
+
+    /// Content of sidecar of `post`, `url` is url of saved file
+    pub fn render(&self, post: &Post, url: &str) -> Vec<u8> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(post).expect("post is serializable"),
+            Self::Txt => post
+                .tags
+                .split_whitespace()
+                .flat_map(|x| [x, "\n"])
+                .collect::<String>()
+                .into_bytes(),
+            Self::Xmp => xmp(post, url).into_bytes(),
+        }
+    }
+}
+
+/// XMP packet, booru fields without standard property are in `shuller` namespace
+fn xmp(post: &Post, url: &str) -> String {
+    let mut xmp = String::from(concat!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+        "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+        " <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        "  <rdf:Description rdf:about=\"\"\n",
+        "    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n",
+        "    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n",
+        "    xmlns:shuller=\"https://crates.io/crates/shuller/\">\n",
+    ));
+    let mut property = |name: &str, value: &str| {
+        if !value.is_empty() {
+            xmp.push_str(&format!("   <{0}>{1}</{0}>\n", name, escape(value)));
+        }
+    };
+    property("dc:identifier", &post.id.to_string());
+    property("dc:source", &post.source);
+    property("shuller:url", url);
+    property("shuller:score", &post.score.to_string());
+    property("shuller:owner", &post.owner);
+    property("shuller:md5", &post.hash);
+    if let Some(rating) = post.rating {
+        property("shuller:rating", &rating.to_string());
+    }
+    if let Some(created_at) = post.created_at {
+        property("xmp:CreateDate", &created_at.to_string());
+    }
+    xmp.push_str("   <dc:subject>\n    <rdf:Bag>\n");
+    for tag in post.tags.split_whitespace() {
+        xmp.push_str(&format!("     <rdf:li>{}</rdf:li>\n", escape(tag)));
+    }
+    xmp.push_str(concat!(
+        "    </rdf:Bag>\n",
+        "   </dc:subject>\n",
+        "  </rdf:Description>\n",
+        " </rdf:RDF>\n",
+        "</x:xmpmeta>\n",
+        "<?xpacket end=\"w\"?>\n",
+    ));
+    xmp
+}
+
+/// Escape text of XML element
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // not allowed in XML 1.0
+            x if x.is_control() && !matches!(x, '\t' | '\n' | '\r') => {}
+            x => escaped.push(x),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule34::meta::Rating;
+
+    #[test]
+    fn render() {
+        let post = Post {
+            id: 7,
+            score: 12,
+            rating: Some(Rating::Questionable),
+            source: "https://example.com/?a=1&b=<2>".to_string(),
+            tags: "dark  fish\u{1}".to_string(),
+            ..Default::default()
+        };
+        let json = Sidecar::Json.render(&post, "");
+        assert_eq!(serde_json::from_slice::<Post>(&json).unwrap(), post);
+
+        let xmp = String::from_utf8(Sidecar::Xmp.render(&post, "https://cdn/7.png")).unwrap();
+        assert!(xmp.contains("<dc:source>https://example.com/?a=1&amp;b=&lt;2&gt;</dc:source>"));
+        assert!(xmp.contains("<shuller:score>12</shuller:score>"));
+        assert!(xmp.contains("<shuller:rating>questionable</shuller:rating>"));
+        assert!(xmp.contains("<rdf:li>dark</rdf:li>\n     <rdf:li>fish</rdf:li>"));
+        assert!(!xmp.contains("owner"));
+        assert_eq!(
+            Sidecar::Json.path(Path::new("7.png")),
+            Path::new("7.png.json")
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::rules::rule34::data::Post;
+use crate::rules::rule34::media::extension;
+use crate::rules::rule34::mini_data::MiniPost;
+use crate::rules::rule34::tag_type::TagTypes;
+
+/// Max length of file name on Linux filesystems
+const MAX_COMPONENT: usize = 255;
+/// Room for `.part.meta` suffix of unfinished files and `.json.part` of sidecars
+const RESERVED: usize = 10;
+
+/// Field of [Post] in [Template]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Id,
+    Md5,
+    Ext,
+    Width,
+    Height,
+    Score,
+    Rating,
+    Owner,
+    Artist,
+    Parent,
+    Directory,
+    Image,
+    Status,
+    Source,
+    Kind,
+    Change,
+    CreatedAt,
+    CommentCount,
+    Tags,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "id" => Self::Id,
+            "md5" | "hash" => Self::Md5,
+            "ext" => Self::Ext,
+            "width" => Self::Width,
+            "height" => Self::Height,
+            "score" => Self::Score,
+            "rating" => Self::Rating,
+            "owner" | "uploader" => Self::Owner,
+            "artist" => Self::Artist,
+            "parent" | "parent_id" => Self::Parent,
+            "directory" => Self::Directory,
+            "image" => Self::Image,
+            "status" => Self::Status,
+            "source" => Self::Source,
+            "kind" => Self::Kind,
+            "change" => Self::Change,
+            "created_at" => Self::CreatedAt,
+            "comment_count" => Self::CommentCount,
+            "tags" => Self::Tags,
+            _ => return None,
+        })
+    }
+
+    /// Value of field, `tags` are rendered by [Template::component]
+    fn value(&self, post: &Post, url: &str, types: &TagTypes) -> String {
+        let or_unknown = |x: String| match x.is_empty() {
+            true => "unknown".to_string(),
+            false => x,
+        };
+        match self {
+            Self::Id => post.id.to_string(),
+            Self::Md5 => or_unknown(post.hash.to_ascii_lowercase()),
+            Self::Ext => match extension(url) {
+                "" => "bin".to_string(),
+                x => x.to_ascii_lowercase(),
+            },
+            Self::Width => post.width.to_string(),
+            Self::Height => post.height.to_string(),
+            Self::Score => post.score.to_string(),
+            Self::Rating => or_unknown(post.rating.map(|x| x.to_string()).unwrap_or_default()),
+            Self::Owner => or_unknown(post.owner.clone()),
+            Self::Artist => or_unknown(types.artists(post).join(" ")),
+            Self::Parent => post.parent_id.to_string(),
+            Self::Directory => post.directory.to_string(),
+            Self::Image => or_unknown(post.image.clone()),
+            Self::Status => or_unknown(post.status.clone()),
+            Self::Source => or_unknown(post.source.clone()),
+            Self::Kind => post.media_kind().to_string(),
+            Self::Change => or_unknown(post.change.map(|x| x.to_string()).unwrap_or_default()),
+            Self::CreatedAt => {
+                or_unknown(post.created_at.map(|x| x.to_string()).unwrap_or_default())
+            }
+            Self::CommentCount => post.comment_count.to_string(),
+            Self::Tags => post.tags.split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+/// Part of path component
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    Text(String),
+    Field(Field),
+    /// `{tags}` or `{tags:3}`, at most this number of tags
+    Tags(Option<usize>),
+}
+
+/// Path of saved file relative to directory of [Downloader](super::Downloader)
+///
+/// `{field}` is replaced by field of [Post], `/` makes directories, `{{` and `}}` are braces
+///
+/// Fields: `id`, `md5` (`hash`), `ext`, `width`, `height`, `score`, `rating`,
+/// `owner` (`uploader`, it isn't artist), `artist`, `parent`, `directory`, `image`, `status`,
+/// `source`, `kind`, `change`, `created_at`, `comment_count`, `tags` and `tags:N` with first `N` tags
+///
+/// Api of posts doesn't tell types of tags, so `artist` is rendered by [Template::render_with]
+/// with [TagTypes] of [R34Client::tag_types](crate::rules::rule34::client::R34Client::tag_types),
+/// artists are joined by space and it's `unknown` without them.
+/// [Downloader::download_pages](super::Downloader::download_pages) gets types by itself
+///
+/// Every file name is safe for Linux: `/` and control chars in values become `_`
+/// and long tag lists are cut by whole tags to fit in 255 bytes
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::download::Template;
+///
+/// let template: Template = "{uploader}/{tags:2}_{id}.{ext}".parse().unwrap();
+/// let post = Post {
+///     id: 5,
+///     owner: "molly".to_string(),
+///     tags: "dark fish sea".to_string(),
+///     file_url: "https://cdn/images/5.png".to_string(),
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     template.render(&post, &post.file_url),
+///     std::path::PathBuf::from("molly/dark fish_5.png")
+/// );
+///
+/// assert!("{id".parse::<Template>().is_err());
+/// assert!("{views}.{ext}".parse::<Template>().is_err());
+///
+/// let template: Template = "{artist}/{id}.{ext}".parse().unwrap();
+/// let types: TagTypes = [("molly", TagType::Artist)].into_iter().collect();
+/// let post = Post { tags: "dark molly".to_string(), ..post };
+/// assert_eq!(
+///     template.render_with(&post, &post.file_url, &types),
+///     std::path::PathBuf::from("molly/5.png")
+/// );
+/// assert_eq!(
+///     template.render(&post, &post.file_url),
+///     std::path::PathBuf::from("unknown/5.png")
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Template {
+    source: String,
+    components: Vec<Vec<Segment>>,
+}
+
+/// `{id}.{ext}`
+impl Default for Template {
+    fn default() -> Self {
+        Self::DEFAULT.parse().expect("default template is valid")
+    }
+}
+
+impl Template {
+    /// Template of [Template::default]
+    pub const DEFAULT: &'static str = "{id}.{ext}";
+
+    /// Relative path of `post`, `url` is url of saved rendition, `artist` is `unknown`
+    #[inline]
+    pub fn render(&self, post: &Post, url: &str) -> PathBuf {
+        self.render_with(post, url, &TagTypes::default())
+    }
+
+    /// Relative path of `post`, `url` is url of saved rendition, `artist` is found by `types`
+    pub fn render_with(&self, post: &Post, url: &str, types: &TagTypes) -> PathBuf {
+        self.components
+            .iter()
+            .map(|x| Self::component(x, post, url, types))
+            .collect()
+    }
+
+    /// Check if `artist` field is used, so [TagTypes] are needed
+    pub fn has_artist(&self) -> bool {
+        self.components
+            .iter()
+            .flatten()
+            .any(|x| *x == Segment::Field(Field::Artist))
+    }
+
+    /// Relative path of `file_url` of `post`
+    #[inline]
+    pub fn render_mini(&self, post: &MiniPost<'_>) -> PathBuf {
+        self.render(post.post(), post.file_url())
+    }
+
+    /// Render one file or directory name
+    fn component(segments: &[Segment], post: &Post, url: &str, types: &TagTypes) -> String {
+        let tags: Vec<&str> = post.tags.split_whitespace().collect();
+        let render = |cut: usize| -> String {
+            let name: String = segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Text(x) => x.clone(),
+                    Segment::Field(field) => sanitize(&field.value(post, url, types)),
+                    Segment::Tags(limit) => {
+                        let limit = limit.unwrap_or(usize::MAX).min(tags.len());
+                        sanitize(&tags[..limit.saturating_sub(cut)].join(" "))
+                    }
+                })
+                .collect();
+            match name.trim() {
+                "" | "." | ".." => "_".to_string(),
+                x => x.to_string(),
+            }
+        };
+        let max = MAX_COMPONENT - RESERVED;
+        let has_tags = segments.iter().any(|x| matches!(x, Segment::Tags(_)));
+        // drop tags from the end until name fits
+        let mut name = render(0);
+        let mut cut = 0;
+        while has_tags && name.len() > max && cut < tags.len() {
+            cut += 1;
+            name = render(cut);
+        }
+        truncate(name, max)
+    }
+
+    /// Find posts which get the same path, the first one of them keeps it
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    /// use shuller::download::Template;
+    ///
+    /// let template: Template = "{md5}.{ext}".parse().unwrap();
+    /// let post = |id| Post { id, hash: "abc".to_string(), ..Default::default() };
+    /// let collisions = template.collisions([post(1), post(2)].iter(), |x| &x.file_url);
+    /// assert_eq!((collisions[0].first, collisions[0].second), (1, 2));
+    /// ```
+    pub fn collisions<'a>(
+        &self,
+        posts: impl IntoIterator<Item = &'a Post>,
+        url: impl Fn(&'a Post) -> &'a str,
+    ) -> Vec<Collision> {
+        let mut seen: HashMap<PathBuf, i64> = HashMap::new();
+        let mut collisions = vec![];
+        for post in posts {
+            let path = self.render(post, url(post));
+            match seen.get(&path) {
+                Some(first) => collisions.push(Collision {
+                    path,
+                    first: *first,
+                    second: post.id,
+                }),
+                None => {
+                    seen.insert(path, post.id);
+                }
+            }
+        }
+        collisions
+    }
+
+    /// Text of template
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+/// Two posts with the same path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// Relative path of both posts
+    pub path: PathBuf,
+    /// Id of post which keeps path
+    pub first: i64,
+    /// Id of other post
+    pub second: i64,
+}
+
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Err(TemplateError::Empty);
+        }
+        if s.starts_with('/') {
+            return Err(TemplateError::Absolute);
+        }
+        let mut components = vec![vec![]];
+        let mut text = String::new();
+        let mut chars = s.char_indices().peekable();
+        while let Some((index, char)) = chars.next() {
+            match char {
+                '{' if chars.peek().map(|x| x.1) == Some('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().map(|x| x.1) == Some('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let end = s[index..]
+                        .find('}')
+                        .map(|x| x + index)
+                        .ok_or(TemplateError::Unclosed(index))?;
+                    let segment = parse_field(&s[index + 1..end])?;
+                    let current = components.last_mut().expect("one component at least");
+                    if !text.is_empty() {
+                        current.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    current.push(segment);
+                    while chars.peek().is_some_and(|x| x.0 <= end) {
+                        chars.next();
+                    }
+                }
+                '}' => return Err(TemplateError::Unexpected(index)),
+                '/' => {
+                    let mut current = components.pop().expect("one component at least");
+                    if !text.is_empty() {
+                        current.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    check_component(&current)?;
+                    components.push(current);
+                    components.push(vec![]);
+                }
+                '\0' => return Err(TemplateError::InvalidChar(index)),
+                x => text.push(x),
+            }
+        }
+        let mut current = components.pop().expect("one component at least");
+        if !text.is_empty() {
+            current.push(Segment::Text(text));
+        }
+        check_component(&current)?;
+        components.push(current);
+        Ok(Self {
+            source: s.to_string(),
+            components,
+        })
+    }
+}
+
+/// Parse `tags:3` of `{tags:3}`
+fn parse_field(text: &str) -> Result<Segment, TemplateError> {
+    let (name, argument) = match text.split_once(':') {
+        Some((name, argument)) => (name.trim(), Some(argument.trim())),
+        None => (text.trim(), None),
+    };
+    let field = Field::parse(name).ok_or_else(|| TemplateError::UnknownField(name.to_string()))?;
+    match (field, argument) {
+        (Field::Tags, None) => Ok(Segment::Tags(None)),
+        (Field::Tags, Some(argument)) => match argument.parse() {
+            Ok(limit) if limit > 0 => Ok(Segment::Tags(Some(limit))),
+            _ => Err(TemplateError::InvalidArgument(
+                name.to_string(),
+                argument.to_string(),
+            )),
+        },
+        (field, None) => Ok(Segment::Field(field)),
+        (_, Some(argument)) => Err(TemplateError::InvalidArgument(
+            name.to_string(),
+            argument.to_string(),
+        )),
+    }
+}
+
+/// Component must not be empty or `..`
+fn check_component(segments: &[Segment]) -> Result<(), TemplateError> {
+    match segments {
+        [] => Err(TemplateError::EmptyComponent),
+        [Segment::Text(x)] if x.trim() == "." || x.trim() == ".." => {
+            Err(TemplateError::EmptyComponent)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Replace `/` and control chars, which can't be in file name
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|x| match x {
+            '/' | '\\' => '_',
+            x if x.is_control() => '_',
+            x => x,
+        })
+        .collect()
+}
+
+/// Cut `name` to `max` bytes on char boundary, extension after the last `.` is kept
+fn truncate(name: String, max: usize) -> String {
+    if name.len() <= max {
+        return name;
+    }
+    let extension = match name.rsplit_once('.') {
+        Some((_, x)) if x.len() < 16 => &name[name.len() - x.len() - 1..],
+        _ => "",
+    };
+    let mut end = max - extension.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", name[..end].trim_end(), extension)
+}
+
+/// Error of parsing [Template]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// Template has no chars
+    Empty,
+    /// Template starts with `/`, path must be relative
+    Absolute,
+    /// `//`, trailing `/`, `.` or `..` between slashes
+    EmptyComponent,
+    /// `{` at this byte without `}`
+    Unclosed(usize),
+    /// `}` at this byte without `{`
+    Unexpected(usize),
+    /// Char at this byte can't be in path
+    InvalidChar(usize),
+    /// `{name}` isn't field of post
+    UnknownField(String),
+    /// Field doesn't accept this argument
+    InvalidArgument(String, String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("template is empty"),
+            Self::Absolute => f.write_str("template must be relative path"),
+            Self::EmptyComponent => f.write_str("template has empty, `.` or `..` directory"),
+            Self::Unclosed(x) => write!(f, "`{{` at {} isn't closed", x),
+            Self::Unexpected(x) => write!(f, "unexpected `}}` at {}", x),
+            Self::InvalidChar(x) => write!(f, "invalid char at {}", x),
+            Self::UnknownField(x) => write!(f, "unknown field {:?}", x),
+            Self::InvalidArgument(field, x) => {
+                write!(f, "invalid argument {:?} of field {:?}", x, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule34::meta::Rating;
+    use crate::rules::rule34::tag_type::TagType;
+
+    fn post() -> Post {
+        Post {
+            id: 42,
+            hash: "ABCDEF".to_string(),
+            owner: "some/one".to_string(),
+            rating: Some(Rating::Safe),
+            tags: " dark  fish sea ".to_string(),
+            file_url: "https://cdn/images/42.WEBM?1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn render(template: &str) -> String {
+        let post = post();
+        let template: Template = template.parse().unwrap();
+        template
+            .render(&post, &post.file_url)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn render_fields() {
+        assert_eq!(render("{id}_{md5}.{ext}"), "42_abcdef.webm");
+        assert_eq!(
+            render("{uploader}/{rating}/{id}.{ext}"),
+            "some_one/safe/42.webm"
+        );
+        assert_eq!(render("{tags:2}_{id}"), "dark fish_42");
+        assert_eq!(render("{tags:10}"), "dark fish sea");
+        assert_eq!(render("{{{id}}}-{kind}"), "{42}-video");
+        assert_eq!(render("{status}/{created_at}"), "unknown/unknown");
+        assert_eq!(Template::default().as_str(), "{id}.{ext}");
+    }
+
+    #[test]
+    fn artist() {
+        let post = post();
+        let template: Template = "{artist}/{id}.{ext}".parse().unwrap();
+        assert!(template.has_artist());
+        assert!(!Template::default().has_artist());
+        let render = |types: TagTypes| template.render_with(&post, &post.file_url, &types);
+        assert_eq!(
+            render(TagTypes::default()),
+            PathBuf::from("unknown/42.webm")
+        );
+        let types = [("sea", TagType::Artist), ("dark", TagType::Artist)];
+        assert_eq!(
+            render(types.into_iter().collect()),
+            PathBuf::from("dark sea/42.webm")
+        );
+    }
+
+    #[test]
+    fn long_tags() {
+        let post = Post {
+            id: 1,
+            tags: (0..100)
+                .map(|x| format!("tag_{:03}", x))
+                .collect::<Vec<_>>()
+                .join(" "),
+            ..Default::default()
+        };
+        let template: Template = "{tags}_{id}.{ext}".parse().unwrap();
+        let name = template.render(&post, "https://cdn/1.png");
+        let name = name.to_str().unwrap();
+        assert!(name.len() <= MAX_COMPONENT - RESERVED);
+        // whole tags are dropped
+        assert!(name.ends_with(" tag_029_1.png"), "{}", name);
+
+        let name = truncate("ы".repeat(200) + ".png", 245);
+        assert!(name.len() <= 245);
+        assert!(name.ends_with("ы.png"));
+    }
+
+    #[test]
+    fn errors() {
+        for (template, error) in [
+            ("", TemplateError::Empty),
+            ("/{id}", TemplateError::Absolute),
+            ("{id}//{ext}", TemplateError::EmptyComponent),
+            ("../{id}", TemplateError::EmptyComponent),
+            ("{id}/", TemplateError::EmptyComponent),
+            ("{id", TemplateError::Unclosed(0)),
+            ("id}", TemplateError::Unexpected(2)),
+            ("{views}", TemplateError::UnknownField("views".to_string())),
+            (
+                "{tags:x}",
+                TemplateError::InvalidArgument("tags".to_string(), "x".to_string()),
+            ),
+            (
+                "{id:3}",
+                TemplateError::InvalidArgument("id".to_string(), "3".to_string()),
+            ),
+        ] {
+            assert_eq!(template.parse::<Template>(), Err(error), "{}", template);
+        }
+    }
+}
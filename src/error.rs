@@ -0,0 +1,146 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::rules::rule34::validation::ValidationErrors;
+use crate::transport::StatusCode;
+
+/// Result of all shuller requests
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Error of shuller requests
+#[derive(Debug)]
+pub enum Error {
+    /// Request wasn't sent or response wasn't received
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// Server answered with not success status
+    Status(StatusCode),
+    /// Server answered with not success status and asked to wait by `Retry-After`
+    Throttled(StatusCode, Duration),
+    /// Body of response isn't expected json
+    Decode(serde_json::Error),
+    /// Base url isn't valid url
+    InvalidUrl(String, url::ParseError),
+    /// Params don't pass [R34Params::validate](crate::rules::rule34::params::R34Params::validate)
+    Validation(ValidationErrors),
+    /// File can't be read or written
+    Io(std::io::Error),
+    /// Request isn't found in cassette of `testing` feature, it's strict
+    NotRecorded {
+        /// Path of cassette
+        cassette: std::path::PathBuf,
+        /// Url without credentials
+        url: String,
+    },
+    /// md5 of downloaded file isn't `hash` of post
+    Checksum {
+        /// `hash` of post
+        expected: String,
+        /// md5 of downloaded bytes
+        actual: String,
+    },
+}
+
+impl Error {
+    /// Wrap any error of [Transport](crate::transport::Transport)
+    #[inline]
+    pub fn transport(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Transport(error.into())
+    }
+
+    /// Check if request may succeed next time
+    ///
+    /// Transport errors, `5xx`, `408 Request Timeout`, `429 Too Many Requests`
+    /// and wrong checksum, file may be broken by cdn
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Transport(_) | Error::Throttled(_, _) | Error::Checksum { .. } => true,
+            Error::Status(status) => {
+                status.is_server_error()
+                    || *status == StatusCode::REQUEST_TIMEOUT
+                    || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::Decode(_)
+            | Error::InvalidUrl(_, _)
+            | Error::Validation(_)
+            | Error::Io(_)
+            | Error::NotRecorded { .. } => false,
+        }
+    }
+
+    /// Time to wait which was asked by server
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Throttled(_, wait) => Some(*wait),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Transport(x) => write!(f, "transport error: {}", x),
+            Error::Status(x) => write!(f, "unexpected status: {}", x),
+            Error::Throttled(x, wait) => {
+                write!(f, "unexpected status: {}, retry after {:?}", x, wait)
+            }
+            Error::Decode(x) => write!(f, "decode error: {}", x),
+            Error::InvalidUrl(url, x) => write!(f, "invalid url {:?}: {}", url, x),
+            Error::Validation(x) => x.fmt(f),
+            Error::Io(x) => write!(f, "io error: {}", x),
+            Error::NotRecorded { cassette, url } => write!(
+                f,
+                "request isn't recorded in cassette {}: {}",
+                cassette.display(),
+                url
+            ),
+            Error::Checksum { expected, actual } => {
+                write!(f, "md5 mismatch: expected {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(x) => Some(x.as_ref()),
+            Error::Status(_)
+            | Error::Throttled(_, _)
+            | Error::Checksum { .. }
+            | Error::NotRecorded { .. } => None,
+            Error::Decode(x) => Some(x),
+            Error::InvalidUrl(_, x) => Some(x),
+            Error::Validation(x) => Some(x),
+            Error::Io(x) => Some(x),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    #[inline]
+    fn from(value: serde_json::Error) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<ValidationErrors> for Error {
+    #[inline]
+    fn from(value: ValidationErrors) -> Self {
+        Self::Validation(value)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    #[inline]
+    fn from(value: reqwest::Error) -> Self {
+        Self::Transport(Box::new(value))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
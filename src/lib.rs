@@ -71,14 +71,54 @@
 /// and for output own structure you need create own struct which implemented [serde::Deserialize]
 pub mod rules;
 
+/// Sync api, enabled by `blocking` feature
+#[cfg(feature = "blocking")]
+pub mod blocking;
+/// Cache of responses, see [cache::Cache]
+pub mod cache;
+/// Saving of media files, enabled by `download` feature, see [download::Downloader]
+#[cfg(feature = "download")]
+pub mod download;
+/// Errors of requests
+pub mod error;
+/// Limit of requests rate, see [rate_limit::RateLimiter]
+pub mod rate_limit;
+/// Retry of failed requests, see [retry::RetryPolicy]
+pub mod retry;
+/// Local api server, enabled by `serve` feature, see [serve::Server]
+#[cfg(feature = "serve")]
+pub mod serve;
+/// Fake api for tests, enabled by `testing` feature, see [testing::FakeBooru]
+#[cfg(feature = "testing")]
+pub mod testing;
+/// Way to send requests, see [transport::Transport]
+pub mod transport;
+
+#[cfg(test)]
+mod temp_dir;
+
 /// Just all that you need
 #[allow(unused)]
 pub mod prelude {
     #[cfg(feature = "rand")]
     pub use crate::{random_usize, random_usize_vec, random_usize_vec_cloned};
 
+    #[cfg(feature = "blocking")]
+    pub use crate::blocking::BlockingPages;
+    pub use crate::cache::{Cache, CacheMode};
+    pub use crate::rate_limit::RateLimiter;
+    pub use crate::retry::{Jitter, RetryPolicy};
+    pub use crate::rules::rule34::blacklist::Blacklist;
+    pub use crate::rules::rule34::client::R34Client;
+    pub use crate::rules::rule34::credentials::Credentials;
     pub use crate::rules::rule34::data::{Post, Posts};
-    pub use crate::rules::rule34::params::R34Params;
+    pub use crate::rules::rule34::media::MediaKind;
+    pub use crate::rules::rule34::meta::{Compare, MetaTag, Rating, SortKey, SortOrder};
+    pub use crate::rules::rule34::pages::Pages;
+    pub use crate::rules::rule34::params::{R34Params, R34ParamsOwned};
+    pub use crate::rules::rule34::query::{Dialect, Query};
+    pub use crate::rules::rule34::safety::SafetyPolicy;
+    pub use crate::rules::rule34::tag_type::{TagType, TagTypes};
     pub use crate::{tag_suppress, toggler, R34};
 
     #[cfg(feature = "rand")]
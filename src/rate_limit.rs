@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use uller::Url;
+
+/// Token bucket of one host
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+/// Token bucket rate limiter, shared across tasks and keyed by host
+///
+/// Cheap to clone, all clones share the same buckets
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// // 2 requests per second, up to 5 requests at once
+/// let limiter = RateLimiter::new(2.0, 5);
+/// let client = R34Client::init().rate_limiter(limiter.clone());
+/// let other_client = R34Client::init().rate_limiter(limiter);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: u32,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    /// Make limiter with `requests_per_second` and `burst` size,
+    /// `burst` is max number of requests which can be sent at once
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        Self {
+            requests_per_second: match requests_per_second.is_finite() {
+                true => requests_per_second.max(f64::MIN_POSITIVE),
+                false => f64::MAX,
+            },
+            burst: burst.max(1),
+            buckets: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    #[inline]
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Key of bucket for `url`
+    fn key(url: &Url) -> String {
+        match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => url.scheme().to_string(),
+        }
+    }
+
+    /// Take token for host of `url` or get time to wait for it
+    fn try_acquire(&self, url: &Url, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|x| x.into_inner());
+        let bucket = buckets.entry(Self::key(url)).or_insert_with(|| Bucket {
+            tokens: self.burst as f64,
+            updated: now,
+            paused_until: None,
+        });
+        if let Some(until) = bucket.paused_until {
+            if until > now {
+                return Err(until - now);
+            }
+            bucket.paused_until = None;
+        }
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.burst as f64);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let wait = (1.0 - bucket.tokens) / self.requests_per_second;
+        Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+    }
+
+    /// Wait until request to host of `url` is allowed
+    pub async fn acquire(&self, url: &Url) {
+        while let Err(wait) = self.try_acquire(url, Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Max time of [RateLimiter::pause]
+    pub const MAX_PAUSE: Duration = Duration::from_secs(3600);
+
+    /// Stop all requests to host of `url` for `duration`, but not more than [RateLimiter::MAX_PAUSE],
+    /// used for `Retry-After` header
+    pub fn pause(&self, url: &Url, duration: Duration) {
+        let Some(until) = Instant::now().checked_add(duration.min(Self::MAX_PAUSE)) else {
+            return;
+        };
+        let mut buckets = self.buckets.lock().unwrap_or_else(|x| x.into_inner());
+        let bucket = buckets.entry(Self::key(url)).or_insert_with(|| Bucket {
+            tokens: 0.0,
+            updated: Instant::now(),
+            paused_until: None,
+        });
+        bucket.paused_until = bucket.paused_until.max(Some(until));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{}/index.php", host)).unwrap()
+    }
+
+    #[test]
+    fn burst() {
+        let limiter = RateLimiter::new(1.0, 2);
+        let now = Instant::now();
+        assert!(limiter.try_acquire(&url("a"), now).is_ok());
+        assert!(limiter.try_acquire(&url("a"), now).is_ok());
+        let wait = limiter.try_acquire(&url("a"), now).unwrap_err();
+        assert!(wait <= Duration::from_secs(1));
+        // other host has own bucket
+        assert!(limiter.try_acquire(&url("b"), now).is_ok());
+        // tokens are refilled
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.try_acquire(&url("a"), later).is_ok());
+        assert!(limiter.try_acquire(&url("a"), later).is_err());
+    }
+
+    #[test]
+    fn pause() {
+        let limiter = RateLimiter::new(100.0, 10);
+        limiter.pause(&url("a"), Duration::from_secs(60));
+        let wait = limiter.try_acquire(&url("a"), Instant::now()).unwrap_err();
+        assert!(wait > Duration::from_secs(50));
+        assert!(limiter.try_acquire(&url("b"), Instant::now()).is_ok());
+
+        // huge `Retry-After` doesn't overflow
+        limiter.pause(&url("c"), Duration::MAX);
+        let wait = limiter.try_acquire(&url("c"), Instant::now()).unwrap_err();
+        assert!(wait <= RateLimiter::MAX_PAUSE);
+    }
+
+    #[tokio::test]
+    async fn shared() {
+        let limiter = RateLimiter::new(50.0, 1);
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire(&url("a")).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(35));
+    }
+}
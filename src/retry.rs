@@ -0,0 +1,324 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tinyrand::{Rand, Seeded, StdRand};
+use uller::Url;
+
+use crate::error::{Error, Result};
+use crate::rules::rule34::credentials::redact_url;
+
+/// Randomization of delay between attempts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Delay as is
+    None,
+    /// Random delay in `0..=delay`
+    #[default]
+    Full,
+    /// Random delay in `delay/2..=delay`
+    Equal,
+}
+
+/// Info about failed attempt, given to [RetryPolicy::on_retry]
+#[derive(Debug)]
+pub struct RetryEvent<'a> {
+    /// Number of failed attempt, starts from 1
+    pub attempt: u32,
+    /// Max number of attempts
+    pub max_attempts: u32,
+    /// Delay before next attempt
+    pub delay: Duration,
+    /// Url of request, `api_key` is hidden
+    pub url: &'a Url,
+    /// Error of failed attempt
+    pub error: &'a Error,
+}
+
+type RetryHook = Arc<dyn Fn(&RetryEvent<'_>) + Send + Sync>;
+type RetryFilter = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Retry of failed requests with exponential backoff and jitter
+///
+/// Delay before attempt `n` is `base_delay * 2^(n - 1)`, or time from `Retry-After` header
+/// if server sent it, but not more than `max_delay`
+///
+/// Every retry is reported by [RetryPolicy::on_retry] hook and `tracing` event
+///
+/// ```
+/// use std::time::Duration;
+/// use shuller::prelude::*;
+///
+/// let client = R34Client::init().retry(
+///     RetryPolicy::init()
+///         .max_attempts(5)
+///         .base_delay(Duration::from_millis(200))
+///         .max_delay(Duration::from_secs(10))
+///         .jitter(Jitter::Full)
+///         .on_retry(|event| println!("retry {} of {}: {}", event.attempt, event.max_attempts, event.error)),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: Jitter,
+    retry_if: Option<RetryFilter>,
+    on_retry: Option<RetryHook>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: Jitter::default(),
+            retry_if: None,
+            on_retry: None,
+        }
+    }
+}
+
+impl Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("retry_if", &self.retry_if.is_some())
+            .field("on_retry", &self.on_retry.is_some())
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Init policy: 3 attempts, 500ms base delay, 30s max delay, full jitter
+    #[inline]
+    pub fn init() -> Self {
+        Self::default()
+    }
+
+    /// Policy without retries
+    #[inline]
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Set max number of attempts, first attempt is counted too
+    #[inline]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set delay before second attempt
+    #[inline]
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Set max delay between attempts
+    #[inline]
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Set [Jitter]
+    #[inline]
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set which errors are retried, by default [Error::is_retryable]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    /// use shuller::error::Error;
+    ///
+    /// let policy = RetryPolicy::init().retry_if(|error| matches!(error, Error::Transport(_)));
+    /// ```
+    #[inline]
+    pub fn retry_if(mut self, retry_if: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_if = Some(Arc::new(retry_if));
+        self
+    }
+
+    /// Set hook which is called before every retry
+    #[inline]
+    pub fn on_retry(mut self, on_retry: impl Fn(&RetryEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.on_retry = Some(Arc::new(on_retry));
+        self
+    }
+
+    /// Check if `error` must be retried
+    fn should_retry(&self, error: &Error) -> bool {
+        match &self.retry_if {
+            Some(retry_if) => retry_if(error),
+            None => error.is_retryable(),
+        }
+    }
+
+    /// Delay before next attempt after failed `attempt`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(exp).min(self.max_delay);
+        let millis = delay.as_millis().min(u64::MAX as u128) as u64;
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => Duration::from_millis(random_u64(millis)),
+            Jitter::Equal => Duration::from_millis(millis / 2 + random_u64(millis - millis / 2)),
+        }
+    }
+
+    /// Time to wait which was asked by server in `error`, but not more than `max_delay`
+    pub(crate) fn retry_after(&self, error: &Error) -> Option<Duration> {
+        error.retry_after().map(|x| x.min(self.max_delay))
+    }
+
+    /// Run `attempt` until success, not retryable error or end of attempts
+    pub async fn run<T, F, Fut>(&self, url: &Url, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut number = 1;
+        loop {
+            let error = match attempt().await {
+                Ok(x) => return Ok(x),
+                Err(error) => error,
+            };
+            if number >= self.max_attempts || !self.should_retry(&error) {
+                return Err(error);
+            }
+            let delay = self
+                .retry_after(&error)
+                .unwrap_or_else(|| self.delay(number));
+            let url = redact_url(url);
+            tracing::warn!(
+                attempt = number,
+                max_attempts = self.max_attempts,
+                delay_ms = delay.as_millis() as u64,
+                url = %url,
+                error = %error,
+                "request failed, retrying"
+            );
+            if let Some(on_retry) = &self.on_retry {
+                on_retry(&RetryEvent {
+                    attempt: number,
+                    max_attempts: self.max_attempts,
+                    delay,
+                    url: &url,
+                    error: &error,
+                });
+            }
+            tokio::time::sleep(delay).await;
+            number += 1;
+        }
+    }
+}
+
+/// Random number in `0..=max`
+pub(crate) fn random_u64(max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut rand = StdRand::seed(((time >> 64) ^ time) as u64);
+    rand.next_lim_u64(max.saturating_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::transport::StatusCode;
+
+    fn url() -> Url {
+        Url::parse("https://api.rule34.xxx/index.php?api_key=secret").unwrap()
+    }
+
+    #[test]
+    fn delay() {
+        let policy = RetryPolicy::init()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(350))
+            .jitter(Jitter::None);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(350));
+        assert_eq!(policy.delay(100), Duration::from_millis(350));
+
+        let policy = policy.jitter(Jitter::Equal);
+        for attempt in 1..5 {
+            let delay = policy.delay(attempt);
+            assert!(delay <= Duration::from_millis(350));
+            assert!(delay >= Duration::from_millis(50));
+        }
+
+        // `Retry-After` is limited by max delay
+        let error = Error::Throttled(StatusCode::TOO_MANY_REQUESTS, Duration::MAX);
+        assert_eq!(policy.retry_after(&error), Some(Duration::from_millis(350)));
+    }
+
+    #[tokio::test]
+    async fn retries() {
+        let calls = AtomicU32::new(0);
+        let events = Arc::new(AtomicU32::new(0));
+        let events_hook = events.clone();
+        let policy = RetryPolicy::init()
+            .max_attempts(4)
+            .base_delay(Duration::from_millis(1))
+            .on_retry(move |event| {
+                assert!(!event.url.as_str().contains("secret"));
+                events_hook.fetch_add(1, Ordering::SeqCst);
+            });
+        let result = policy
+            .run(&url(), || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(Error::Status(StatusCode::BAD_GATEWAY)),
+                    _ => Ok(5),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 5);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(events.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up() {
+        let calls = AtomicU32::new(0);
+        let policy = RetryPolicy::init()
+            .max_attempts(3)
+            .base_delay(Duration::from_millis(1));
+        let result: Result<()> = policy
+            .run(&url(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::Status(StatusCode::SERVICE_UNAVAILABLE))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy
+            .run(&url(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(Error::Status(StatusCode::NOT_FOUND))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -12,7 +12,11 @@ macro_rules! tag_suppress {
         let nt: Vec<String> = $narray.iter().map(|x| format!("-{}", x)).collect();
         let nt = nt.join(" ");
         let pt = $array.join(" ");
-        format!("{} {}", pt, nt)
+        [pt, nt]
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<String>>()
+            .join(" ")
     }};
 }
 
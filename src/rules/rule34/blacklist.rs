@@ -0,0 +1,207 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::data::Post;
+use super::query::{ParseError, Query};
+
+/// Blacklist of booru websites, checked on client side
+///
+/// Every line is [Query] in `Rule34` syntax, so tags of one line are joined by `And`
+/// and lines are joined by `Or`. Post is blocked if it matches any line
+///
+/// Unlike `negative_tags` it has no limit of length, because nothing is sent to api
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let blacklist: Blacklist = "
+///     ai_generated
+///     rating:explicit dark
+///     score:<0
+/// "
+/// .parse()
+/// .unwrap();
+///
+/// let post = |tags: &str, rating, score| Post {
+///     tags: tags.to_string(),
+///     rating: Some(rating),
+///     score,
+///     ..Default::default()
+/// };
+/// assert!(blacklist.blocks(&post("fish ai_generated", Rating::Safe, 10)));
+/// assert!(blacklist.blocks(&post("dark fish", Rating::Explicit, 10)));
+/// assert!(!blacklist.blocks(&post("dark fish", Rating::Safe, 10)));
+/// assert!(blacklist.blocks(&post("dark fish", Rating::Safe, -1)));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Blacklist {
+    lines: Vec<Query>,
+}
+
+impl Blacklist {
+    /// Init empty blacklist, it blocks nothing
+    #[inline]
+    pub fn init() -> Self {
+        Self::default()
+    }
+
+    /// Add line, post is blocked if it matches `query`
+    #[inline]
+    pub fn line(mut self, query: Query) -> Self {
+        self.lines.push(query);
+        self
+    }
+
+    /// Lines of blacklist
+    #[inline]
+    pub fn lines(&self) -> &[Query] {
+        &self.lines
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Check if `post` matches any line
+    #[inline]
+    pub fn blocks(&self, post: &Post) -> bool {
+        self.lines.iter().any(|x| x.matches(post))
+    }
+
+    /// Check if `post` doesn't match any line
+    #[inline]
+    pub fn allows(&self, post: &Post) -> bool {
+        !self.blocks(post)
+    }
+}
+
+impl Extend<Query> for Blacklist {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Query>>(&mut self, iter: T) {
+        self.lines.extend(iter)
+    }
+}
+
+impl FromIterator<Query> for Blacklist {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Query>>(iter: T) -> Self {
+        Self {
+            lines: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Render lines in `Rule34` syntax, one per line
+impl Display for Blacklist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse one [Query] per line, empty lines are skipped
+impl FromStr for Blacklist {
+    type Err = BlacklistError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                line.parse().map_err(|error| BlacklistError {
+                    line: index + 1,
+                    error,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Serialized as text, the same as [Display]
+impl Serialize for Blacklist {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Blacklist {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Error of parsing [Blacklist]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlacklistError {
+    /// Number of line, starting from `1`
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl Display for BlacklistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} of blacklist: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for BlacklistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule34::meta::Rating;
+
+    #[test]
+    fn parse() {
+        let blacklist: Blacklist = "ai_generated\n\n  rating:explicit  dark \nscore:<0\nfish*"
+            .parse()
+            .unwrap();
+        assert_eq!(blacklist.len(), 4);
+        assert_eq!(
+            blacklist.to_string(),
+            "ai_generated\nrating:explicit dark\nscore:<0\nfish*"
+        );
+        assert_eq!(blacklist.to_string().parse(), Ok(blacklist.clone()));
+
+        let json = serde_json::to_string(&blacklist).unwrap();
+        assert_eq!(serde_json::from_str::<Blacklist>(&json).unwrap(), blacklist);
+
+        let error = "dark\nscore:<zero".parse::<Blacklist>().unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(Blacklist::init().is_empty());
+    }
+
+    #[test]
+    fn blocks() {
+        let blacklist: Blacklist = "rating:explicit dark\nfish*".parse().unwrap();
+        let post = |tags: &str, rating| Post {
+            tags: tags.to_string(),
+            rating,
+            ..Default::default()
+        };
+        assert!(blacklist.blocks(&post("dark", Some(Rating::Explicit))));
+        assert!(blacklist.allows(&post("dark", Some(Rating::Safe))));
+        assert!(blacklist.allows(&post("dark", None)));
+        assert!(blacklist.blocks(&post("sea fishing", None)));
+        assert!(Blacklist::init().allows(&post("dark", Some(Rating::Explicit))));
+    }
+}
@@ -0,0 +1,732 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use uller::{MakeLink, Url};
+
+use super::credentials::Credentials;
+use super::data::{Post, Posts};
+use super::pages::Pages;
+use super::params::R34Params;
+use super::safety::{SafetyPolicy, Violation, ViolationHook};
+use super::tag_type::TagTypes;
+use crate::cache::{Cache, CacheEntry, CacheMode};
+use crate::error::{Error, Result};
+use crate::rate_limit::RateLimiter;
+use crate::retry::{random_u64, RetryPolicy};
+use crate::transport::{header, Request, ReqwestTransport, StatusCode, Transport};
+
+/// Shared settings for many [R34Params]
+///
+/// Cheap to clone, so one client can be shared across tasks
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// async fn example() {
+///     let client = R34Client::init().credentials(Credentials::new("123", "secret"));
+///     let posts = client
+///         .download(&R34Params::init().positive_tags(vec!["dark"]))
+///         .await
+///         .unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct R34Client {
+    transport: Arc<dyn Transport>,
+    credentials: Option<Credentials>,
+    base_url: Option<String>,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    cache: Option<Cache>,
+    cache_mode: CacheMode,
+    safety: Option<SafetyPolicy>,
+    on_violation: Option<ViolationHook>,
+}
+
+static GLOBAL: OnceLock<RwLock<R34Client>> = OnceLock::new();
+
+impl Default for R34Client {
+    fn default() -> Self {
+        Self {
+            transport: Arc::new(ReqwestTransport::default()),
+            credentials: None,
+            base_url: None,
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
+            cache: None,
+            cache_mode: CacheMode::Default,
+            safety: None,
+            on_violation: None,
+        }
+    }
+}
+
+impl R34Client {
+    /// Init client with default [ReqwestTransport] and [RetryPolicy]
+    #[inline]
+    pub fn init() -> Self {
+        Self::default()
+    }
+
+    /// Set [Transport] for every request
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    /// use shuller::transport::ReqwestTransport;
+    ///
+    /// let client = R34Client::init().transport(ReqwestTransport::new(reqwest::Client::new()));
+    /// ```
+    #[inline]
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Set [Credentials] for every request,
+    /// [Credentials] of [R34Params] take precedence
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let client = R34Client::init().credentials(Credentials::new("123", "secret"));
+    /// let url = client.url_generate(&R34Params::init());
+    /// assert!(url.as_str().ends_with("&api_key=secret&user_id=123"));
+    /// ```
+    #[inline]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Set [RetryPolicy] for every request, use [RetryPolicy::none] to disable retries
+    #[inline]
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set [RateLimiter] for every request,
+    /// share one limiter between clients by cloning it
+    ///
+    /// When server answers `429` or `503` with `Retry-After`,
+    /// all requests to this host wait for it
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let client = R34Client::init().rate_limiter(RateLimiter::new(2.0, 5));
+    /// ```
+    #[inline]
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Set [Cache] of responses, share one cache between clients by cloning it
+    ///
+    /// Stale entries are revalidated by `ETag` and `Last-Modified`
+    #[inline]
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set [CacheMode], clone client to change it for one request
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn example(client: &R34Client) {
+    ///     let params = R34Params::init().positive_tags(vec!["dark"]);
+    ///     let posts = client
+    ///         .clone()
+    ///         .cache_mode(CacheMode::Refresh)
+    ///         .download(&params)
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    #[inline]
+    pub fn cache_mode(mut self, cache_mode: CacheMode) -> Self {
+        self.cache_mode = cache_mode;
+        self
+    }
+
+    /// Set [SafetyPolicy] for every request,
+    /// it's joined with [SafetyPolicy] of [R34Params]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let client = R34Client::init().safety(SafetyPolicy::safe());
+    /// let url = client.url_generate(&R34Params::init().positive_tags(vec!["dark"]));
+    /// assert!(url.as_str().contains("tags=dark+rating%3Asafe"));
+    /// ```
+    #[inline]
+    pub fn safety(mut self, safety: SafetyPolicy) -> Self {
+        self.safety = Some(safety);
+        self
+    }
+
+    /// Call `hook` for every post dropped by [SafetyPolicy],
+    /// violations are logged as warnings anyway
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let client = R34Client::init()
+    ///     .safety(SafetyPolicy::safe())
+    ///     .on_violation(|violation| eprintln!("dropped post {}", violation.id));
+    /// ```
+    #[inline]
+    pub fn on_violation(mut self, hook: impl Fn(&Violation) + Send + Sync + 'static) -> Self {
+        self.on_violation = Some(ViolationHook(Arc::new(hook)));
+        self
+    }
+
+    /// Remove cached response of `params`
+    pub async fn invalidate(&self, params: &R34Params<'_>) -> Result<()> {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&self.try_url_generate(params)?).await;
+        }
+        Ok(())
+    }
+
+    /// Set client which is used by [R34Params::download](uller::JsonDownload::download)
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// R34Client::set_global(R34Client::init().rate_limiter(RateLimiter::new(2.0, 5)));
+    /// ```
+    pub fn set_global(client: R34Client) {
+        let global = GLOBAL.get_or_init(Default::default);
+        *global.write().unwrap_or_else(|x| x.into_inner()) = client;
+    }
+
+    /// Client which is used by [R34Params::download](uller::JsonDownload::download),
+    /// [R34Client::default] until [R34Client::set_global] is called
+    ///
+    /// Its connection pool is shared by every runtime, so if runtimes are started and
+    /// stopped, like in tests, give each of them own client by [R34Client::init]
+    pub fn global() -> R34Client {
+        let global = GLOBAL.get_or_init(Default::default);
+        let client = global.read().unwrap_or_else(|x| x.into_inner());
+        client.clone()
+    }
+
+    /// Set url of api for every request,
+    /// `base_url` of [R34Params] takes precedence
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let client = R34Client::init().base_url("http://localhost:8080/index.php");
+    /// let url = client.try_url_generate(&R34Params::init()).unwrap();
+    /// assert_eq!(url.port(), Some(8080));
+    /// ```
+    #[inline]
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Generate url of `params` with settings of client
+    ///
+    /// **Panics** if `base_url` isn't valid url, use [R34Client::try_url_generate] to handle it
+    #[inline]
+    pub fn url_generate(&self, params: &R34Params<'_>) -> Url {
+        self.try_url_generate(params)
+            .expect("Failed to parse URL with params")
+    }
+
+    /// Generate url of `params` with settings of client, never panics
+    #[inline]
+    pub fn try_url_generate(&self, params: &R34Params<'_>) -> Result<Url> {
+        let (base_url, credentials) = (self.base_url.as_deref(), self.credentials.as_ref());
+        match self.safety {
+            Some(safety) => params
+                .borrowed()
+                .safety(safety)
+                .try_url_with(base_url, credentials),
+            None => params.try_url_with(base_url, credentials),
+        }
+    }
+
+    /// Download [Posts] by `params` with settings of client
+    ///
+    /// Posts which aren't allowed by [SafetyPolicy] are dropped
+    #[inline]
+    pub async fn download(&self, params: &R34Params<'_>) -> Result<Posts> {
+        Ok(self.download_page(params).await?.1)
+    }
+
+    /// Download [Posts] by `params`, also returns number of posts sent by api
+    /// before [SafetyPolicy] dropped some of them
+    pub(crate) async fn download_page(&self, params: &R34Params<'_>) -> Result<(usize, Posts)> {
+        let posts = self.download_url(self.try_url_generate(params)?).await?;
+        let safety = match (self.safety, params.safety) {
+            (Some(client), Some(params)) => Some(client.and(params)),
+            (client, params) => client.or(params),
+        };
+        Ok((posts.len(), self.enforce(safety, posts)))
+    }
+
+    /// Download [Posts] by any [MakeLink] with settings of client
+    ///
+    /// Posts which aren't allowed by [SafetyPolicy] of client are dropped
+    pub async fn download_link(&self, link: &(impl MakeLink + Sync)) -> Result<Posts> {
+        let posts = self.download_url(link.url_generate()).await?;
+        Ok(self.enforce(self.safety, posts))
+    }
+
+    /// Drop posts which aren't allowed by `safety` and report them
+    fn enforce(&self, safety: Option<SafetyPolicy>, posts: Posts) -> Posts {
+        let Some(safety) = safety else {
+            return posts;
+        };
+        let (posts, violations) = safety.filter(posts);
+        for violation in &violations {
+            tracing::warn!(
+                id = violation.id,
+                rating = ?violation.rating,
+                "post isn't allowed by safety policy"
+            );
+            if let Some(hook) = &self.on_violation {
+                (hook.0)(violation);
+            }
+        }
+        posts
+    }
+
+    /// Count posts of `params` with settings of client
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn example() {
+    ///     let count = R34Client::init()
+    ///         .count(&R34Params::init().positive_tags(vec!["dark"]))
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn count(&self, params: &R34Params<'_>) -> Result<u64> {
+        let url = self.try_url_generate(&params.count_params())?;
+        self.get(url, parse_count).await
+    }
+
+    /// Download random post of `params` with settings of client,
+    /// [None] if nothing is found
+    pub async fn random(&self, params: &R34Params<'_>) -> Result<Option<Post>> {
+        const PAGE: u64 = 100;
+        let count = self.count(params).await?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let index = random_u64(count - 1).min(PAGE * u16::MAX as u64);
+        let params = params
+            .borrowed()
+            .limit(PAGE as u16)
+            .page((index / PAGE) as u16);
+        let posts = self.download(&params).await?.data();
+        let index = (index % PAGE) as usize;
+        Ok(posts.get(index).or(posts.last()).cloned())
+    }
+
+    /// Types of `tags` with settings of client, tags which api doesn't know are missing
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn example() {
+    ///     let types = R34Client::init().tag_types(["molly", "fish"]).await.unwrap();
+    ///     println!("{:?}", types.get("molly"));
+    /// }
+    /// ```
+    pub async fn tag_types<'a>(&self, tags: impl IntoIterator<Item = &'a str>) -> Result<TagTypes> {
+        const BATCH: usize = 100;
+        let mut tags: Vec<&str> = tags.into_iter().filter(|x| !x.is_empty()).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        let mut types = TagTypes::default();
+        for names in tags.chunks(BATCH) {
+            types.extend(self.get(self.tag_url(names)?, TagTypes::from_xml).await?);
+        }
+        Ok(types)
+    }
+
+    /// Url of `s=tag` api for `names`
+    fn tag_url(&self, names: &[&str]) -> Result<Url> {
+        let base_url = self
+            .base_url
+            .as_deref()
+            .unwrap_or(R34Params::DEFAULT_BASE_URL);
+        let limit = names.len().to_string();
+        let params = [
+            ("page", "dapi"),
+            ("s", "tag"),
+            ("q", "index"),
+            ("names", &names.join(" ")),
+            ("limit", &limit),
+        ];
+        let mut url = Url::parse_with_params(base_url, params)
+            .map_err(|error| Error::InvalidUrl(base_url.to_string(), error))?;
+        if let Some(credentials) = &self.credentials {
+            credentials.apply(&mut url);
+        }
+        Ok(url)
+    }
+
+    /// Iterate over pages of `params`, starting from `page` of `params`
+    #[inline]
+    pub fn pages(&self, params: &R34Params<'_>) -> Pages {
+        Pages::new(self.clone(), params.clone().into_owned())
+    }
+
+    async fn download_url(&self, url: Url) -> Result<Posts> {
+        self.get(url, Posts::from_json).await
+    }
+
+    /// Get body of `url` through cache and decode it,
+    /// only decoded bodies are cached
+    async fn get<T>(&self, url: Url, decode: fn(&[u8]) -> Result<T>) -> Result<T> {
+        let cache = match &self.cache {
+            Some(cache) if self.cache_mode != CacheMode::Bypass => cache,
+            _ => {
+                let (body, _) = self.fetch(&url, None).await?.unwrap_or_default();
+                return decode(&body);
+            }
+        };
+        let cached = cache.get(&url).await;
+        if let Some(entry) = &cached {
+            if self.cache_mode == CacheMode::Default && entry.is_fresh() {
+                return decode(&entry.body);
+            }
+        }
+        let entry = match (self.fetch(&url, cached.as_ref()).await?, cached) {
+            (Some((body, headers)), _) => cache.entry(body, &headers),
+            // not modified
+            (None, Some(cached)) => cache.revalidated(cached),
+            (None, None) => unreachable!("not modified is returned only for cached entry"),
+        };
+        let decoded = decode(&entry.body)?;
+        cache.insert(&url, entry).await;
+        Ok(decoded)
+    }
+
+    /// Send request with retries, [None] if `cached` entry isn't modified
+    async fn fetch(
+        &self,
+        url: &Url,
+        cached: Option<&CacheEntry>,
+    ) -> Result<Option<(bytes::Bytes, header::HeaderMap)>> {
+        self.retry
+            .run(url, || async {
+                if let Some(limiter) = &self.rate_limiter {
+                    limiter.acquire(url).await;
+                }
+                let mut request = Request::get(url.clone());
+                if let Some(cached) = cached {
+                    if let Some(etag) = &cached.etag {
+                        request = request.header(header::IF_NONE_MATCH.as_str(), etag);
+                    }
+                    if let Some(last_modified) = &cached.last_modified {
+                        request = request.header(header::IF_MODIFIED_SINCE.as_str(), last_modified);
+                    }
+                }
+                let response = self.transport.send(request).await?;
+                if response.status == StatusCode::NOT_MODIFIED && cached.is_some() {
+                    return Ok(None);
+                }
+                let response = response.error_for_status().inspect_err(|error| {
+                    if let (Some(limiter), Some(wait)) =
+                        (&self.rate_limiter, self.retry.retry_after(error))
+                    {
+                        limiter.pause(url, wait);
+                    }
+                })?;
+                let headers = response.headers;
+                Ok(Some((response.body.bytes().await?, headers)))
+            })
+            .await
+    }
+}
+
+/// Get `count` attribute of `<posts count="..">` xml
+fn parse_count(body: &[u8]) -> Result<u64> {
+    let body = String::from_utf8_lossy(body);
+    let count = body
+        .find("<posts")
+        .and_then(|start| body[start..].find("count=\"").map(|x| start + x + 7))
+        .and_then(|start| {
+            let end = body[start..].find('"')? + start;
+            body[start..end].parse().ok()
+        });
+    count.ok_or_else(|| Error::Decode(serde::de::Error::custom("no count of posts in xml")))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::rules::rule34::meta::Rating;
+    use crate::transport::{Response, StatusCode};
+
+    #[test]
+    fn credentials_precedence() {
+        let client = R34Client::init().credentials(Credentials::new("1", "client"));
+        let url = client.url_generate(&R34Params::init());
+        assert!(url.as_str().ends_with("&api_key=client&user_id=1"));
+
+        let params = R34Params::init().credentials(Credentials::new("2", "params"));
+        let url = client.url_generate(&params);
+        assert!(url.as_str().ends_with("&api_key=params&user_id=2"));
+        assert!(!url.as_str().contains("client"));
+    }
+
+    #[test]
+    fn base_url_precedence() {
+        let client = R34Client::init().base_url("http://client/index.php");
+        let url = client.url_generate(&R34Params::init());
+        assert_eq!(url.host_str(), Some("client"));
+
+        let params = R34Params::init().base_url("http://params/index.php");
+        let url = client.url_generate(&params);
+        assert_eq!(url.host_str(), Some("params"));
+
+        let client = R34Client::init().base_url("not url");
+        assert!(client.try_url_generate(&R34Params::init()).is_err());
+    }
+
+    #[derive(Debug)]
+    struct Fixed(StatusCode, &'static str);
+
+    #[async_trait]
+    impl Transport for Fixed {
+        async fn send(&self, _: Request) -> Result<Response> {
+            Ok(Response::new(self.0, self.1))
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_transport() {
+        let client = R34Client::init().transport(Fixed(
+            StatusCode::OK,
+            include_str!("../../../tests/fixtures/posts.json"),
+        ));
+        let posts = client.download(&R34Params::init()).await.unwrap();
+        assert_eq!(posts.len(), 3);
+
+        let client = R34Client::init().transport(Fixed(StatusCode::OK, ""));
+        assert!(client
+            .download(&R34Params::init())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let client = R34Client::init()
+            .transport(Fixed(StatusCode::BAD_GATEWAY, ""))
+            .retry(RetryPolicy::none());
+        assert!(client.download(&R34Params::init()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn validation() {
+        let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let client = R34Client::init().transport(Etag(calls.clone()));
+        let result = client.download(&R34Params::init().limit(5000)).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let params = R34Params::init().limit(5000).build_lenient();
+        assert!(client.download(&params).await.is_ok());
+    }
+
+    #[derive(Debug, Default)]
+    struct Flaky(std::sync::atomic::AtomicU32);
+
+    #[async_trait]
+    impl Transport for Flaky {
+        async fn send(&self, _: Request) -> Result<Response> {
+            match self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => Ok(Response::new(StatusCode::SERVICE_UNAVAILABLE, "")),
+                _ => Ok(Response::new(StatusCode::OK, "[]")),
+            }
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Throttling(std::sync::atomic::AtomicU32);
+
+    #[async_trait]
+    impl Transport for Throttling {
+        async fn send(&self, _: Request) -> Result<Response> {
+            match self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                0 => {
+                    Ok(Response::new(StatusCode::TOO_MANY_REQUESTS, "").header("Retry-After", "0"))
+                }
+                _ => Ok(Response::new(StatusCode::OK, "[]")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let client = R34Client::init()
+            .transport(Throttling::default())
+            .retry(RetryPolicy::init().base_delay(std::time::Duration::from_secs(60)))
+            .rate_limiter(RateLimiter::new(1000.0, 10));
+        // Retry-After takes precedence over base delay
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            client.download(&R34Params::init()),
+        )
+        .await;
+        assert!(result.unwrap().is_ok());
+    }
+
+    /// Counts requests, answers `304` to `If-None-Match`
+    #[derive(Debug, Default)]
+    struct Etag(Arc<std::sync::atomic::AtomicU32>);
+
+    #[async_trait]
+    impl Transport for Etag {
+        async fn send(&self, request: Request) -> Result<Response> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if request.headers.get(header::IF_NONE_MATCH).is_some() {
+                return Ok(Response::new(StatusCode::NOT_MODIFIED, ""));
+            }
+            Ok(Response::new(StatusCode::OK, "[]").header("ETag", "\"v1\""))
+        }
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let calls_count = || calls.load(std::sync::atomic::Ordering::SeqCst);
+        let cache = Cache::new(10);
+        let client = R34Client::init()
+            .transport(Etag(calls.clone()))
+            .cache(cache.clone());
+        let params = R34Params::init();
+
+        client.download(&params).await.unwrap();
+        client.download(&params).await.unwrap();
+        assert_eq!(calls_count(), 1);
+
+        // fresh entry is revalidated
+        let refresh = client.clone().cache_mode(CacheMode::Refresh);
+        refresh.download(&params).await.unwrap();
+        assert_eq!(calls_count(), 2);
+
+        client
+            .clone()
+            .cache_mode(CacheMode::Bypass)
+            .download(&params)
+            .await
+            .unwrap();
+        assert_eq!(calls_count(), 3);
+
+        client.invalidate(&params).await.unwrap();
+        client.download(&params).await.unwrap();
+        assert_eq!(calls_count(), 4);
+
+        // stale entry is revalidated by etag
+        let client = client.cache(cache.ttl(std::time::Duration::ZERO));
+        client.invalidate(&params).await.unwrap();
+        client.download(&params).await.unwrap();
+        client.download(&params).await.unwrap();
+        assert_eq!(calls_count(), 6);
+    }
+
+    #[test]
+    fn count() {
+        let xml =
+            r#"<?xml version="1.0" encoding="UTF-8"?><posts count="1234" offset="0"></posts>"#;
+        assert_eq!(parse_count(xml.as_bytes()).unwrap(), 1234);
+        assert!(parse_count(b"[]").is_err());
+    }
+
+    /// Serves `count` posts by pages of fixture
+    #[derive(Debug)]
+    struct Paged(u64);
+
+    #[async_trait]
+    impl Transport for Paged {
+        async fn send(&self, request: Request) -> Result<Response> {
+            let query = |key: &str| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(k, _)| k == key)
+                    .and_then(|(_, v)| v.parse::<u64>().ok())
+                    .unwrap_or_default()
+            };
+            if query("json") == 0 {
+                let xml = format!(r#"<posts count="{}" offset="0"></posts>"#, self.0);
+                return Ok(Response::new(StatusCode::OK, xml));
+            }
+            let posts: Vec<serde_json::Value> =
+                serde_json::from_str(include_str!("../../../tests/fixtures/posts.json")).unwrap();
+            let start = query("pid") * query("limit");
+            let posts: Vec<_> = (start..self.0.min(start + query("limit")))
+                .map(|n| posts[n as usize % posts.len()].clone())
+                .collect();
+            Ok(Response::new(
+                StatusCode::OK,
+                serde_json::to_vec(&posts).unwrap(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_and_random() {
+        let client = R34Client::init().transport(Paged(7));
+        let params = R34Params::init().limit(3).page(0);
+        assert_eq!(client.count(&params).await.unwrap(), 7);
+
+        let mut pages = client.pages(&params);
+        let mut sizes = vec![];
+        while let Some(posts) = pages.next().await {
+            sizes.push(posts.unwrap().len());
+        }
+        assert_eq!(sizes, vec![3, 3, 1]);
+        assert_eq!(client.pages(&params).collect().await.unwrap().len(), 7);
+
+        assert!(client.random(&params).await.unwrap().is_some());
+        let client = R34Client::init().transport(Paged(0));
+        assert!(client.random(&params).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn retry() {
+        let client = R34Client::init()
+            .transport(Flaky::default())
+            .retry(RetryPolicy::init().base_delay(std::time::Duration::from_millis(1)));
+        assert!(client.download(&R34Params::init()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn safety() {
+        let dropped = Arc::new(std::sync::Mutex::new(vec![]));
+        let hook = dropped.clone();
+        let client = R34Client::init()
+            .transport(Fixed(
+                StatusCode::OK,
+                include_str!("../../../tests/fixtures/posts.json"),
+            ))
+            .safety(SafetyPolicy::max(Rating::Questionable))
+            .on_violation(move |x| hook.lock().unwrap().push(x.id));
+        let posts = client.download(&R34Params::init()).await.unwrap();
+        assert_eq!(posts.len(), 2);
+        assert_eq!(*dropped.lock().unwrap(), vec![10542350]);
+
+        let params = R34Params::init().safety(SafetyPolicy::safe());
+        let url = client.url_generate(&params);
+        assert!(url.as_str().contains("tags=rating%3Asafe&"));
+        let posts = client.download(&params).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts.data_ref()[0].rating, Some(Rating::Safe));
+    }
+}
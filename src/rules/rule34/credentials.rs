@@ -0,0 +1,185 @@
+use std::fmt::{Debug, Display};
+
+use uller::Url;
+
+/// Name of query param with api key
+const API_KEY: &str = "api_key";
+/// Name of query param with user id
+const USER_ID: &str = "user_id";
+/// Replacement of secret values
+const REDACTED: &str = "***";
+
+/// Credentials for `Rule34` DAPI
+///
+/// `api_key` is hidden in [Debug] output
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let credentials = Credentials::new("123", "secret");
+/// assert_eq!(
+///     format!("{:?}", credentials),
+///     r#"Credentials { user_id: "123", api_key: "***" }"#
+/// );
+///
+/// let url = R34Params::init().credentials(credentials).url_generate();
+/// assert!(url.as_str().ends_with("&api_key=secret&user_id=123"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Credentials {
+    user_id: String,
+    api_key: String,
+}
+
+impl Credentials {
+    /// Env variable with user id for [Credentials::from_env]
+    pub const USER_ID_ENV: &'static str = "RULE34_USER_ID";
+    /// Env variable with api key for [Credentials::from_env]
+    pub const API_KEY_ENV: &'static str = "RULE34_API_KEY";
+
+    /// Make credentials from `user_id` and `api_key`
+    #[inline]
+    pub fn new(user_id: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            user_id: user_id.into(),
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Load credentials from [Credentials::USER_ID_ENV] and [Credentials::API_KEY_ENV]
+    #[inline]
+    pub fn from_env() -> Result<Self, CredentialsError> {
+        Self::from_env_vars(Self::USER_ID_ENV, Self::API_KEY_ENV)
+    }
+
+    /// Load credentials from custom env variables
+    pub fn from_env_vars(user_id: &str, api_key: &str) -> Result<Self, CredentialsError> {
+        let var = |name: &str| match std::env::var(name) {
+            Ok(x) if !x.trim().is_empty() => Ok(x.trim().to_string()),
+            _ => Err(CredentialsError::Missing(name.to_string())),
+        };
+        Ok(Self::new(var(user_id)?, var(api_key)?))
+    }
+
+    #[inline]
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    #[inline]
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    /// Append credentials to query of `url`
+    pub fn apply(&self, url: &mut Url) {
+        url.query_pairs_mut()
+            .append_pair(API_KEY, &self.api_key)
+            .append_pair(USER_ID, &self.user_id);
+    }
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user_id", &self.user_id)
+            .field("api_key", &REDACTED)
+            .finish()
+    }
+}
+
+/// Copy of `url` with hidden `api_key`, use it for logs
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::rules::rule34::credentials::redact_url;
+///
+/// let url = R34Params::init()
+///     .credentials(Credentials::new("123", "secret"))
+///     .url_generate();
+/// assert!(!redact_url(&url).as_str().contains("secret"));
+/// ```
+pub fn redact_url(url: &Url) -> Url {
+    redact_keys(url, &[API_KEY])
+}
+
+/// Copy of `url` with hidden `api_key` and `user_id`, use it for stored urls
+pub(crate) fn redact_credentials(url: &Url) -> Url {
+    redact_keys(url, &[API_KEY, USER_ID])
+}
+
+fn redact_keys(url: &Url, keys: &[&str]) -> Url {
+    if !url
+        .query_pairs()
+        .any(|(key, _)| keys.contains(&key.as_ref()))
+    {
+        return url.clone();
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| match keys.contains(&key.as_ref()) {
+            true => (key.into_owned(), REDACTED.to_string()),
+            false => (key.into_owned(), value.into_owned()),
+        })
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url
+}
+
+/// Error of loading [Credentials]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialsError {
+    /// Env variable isn't set or empty
+    Missing(String),
+}
+
+impl Display for CredentialsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialsError::Missing(name) => write!(f, "env variable {} isn't set", name),
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug() {
+        let credentials = Credentials::new("1", "secret");
+        assert!(!format!("{:?}", credentials).contains("secret"));
+        assert!(!format!("{:#?}", Some(credentials)).contains("secret"));
+    }
+
+    #[test]
+    fn redact() {
+        let url = Url::parse("https://api.rule34.xxx/index.php?page=dapi&api_key=secret&user_id=1")
+            .unwrap();
+        assert_eq!(
+            redact_url(&url).as_str(),
+            "https://api.rule34.xxx/index.php?page=dapi&api_key=***&user_id=1"
+        );
+        let url = Url::parse("https://api.rule34.xxx/index.php?page=dapi").unwrap();
+        assert_eq!(redact_url(&url), url);
+    }
+
+    #[test]
+    fn env() {
+        std::env::set_var("SHULLER_TEST_USER", "1");
+        std::env::set_var("SHULLER_TEST_KEY", " secret ");
+        assert_eq!(
+            Credentials::from_env_vars("SHULLER_TEST_USER", "SHULLER_TEST_KEY"),
+            Ok(Credentials::new("1", "secret"))
+        );
+        assert_eq!(
+            Credentials::from_env_vars("SHULLER_TEST_USER", "SHULLER_TEST_MISSING"),
+            Err(CredentialsError::Missing(
+                "SHULLER_TEST_MISSING".to_string()
+            ))
+        );
+    }
+}
@@ -1,56 +1,180 @@
 #[cfg(feature = "rand")]
 use crate::random_usize_vec;
 
-use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::Index;
 
+use serde::{Deserialize, Serialize};
+
+use super::blacklist::Blacklist;
+use super::media::MediaKind;
+use super::meta::{Rating, SortKey, SortOrder};
 use super::mini_data::{MiniPost, MiniPosts};
+use super::time::DateTime;
 
 /// List of [Post]
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Posts(Vec<Post>);
 
 /// # Main data structure
 ///
 /// * Post data from reqwest, which contains urls as well
-#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+///
+/// Decoding is tolerant: missing fields, `null` and numbers in strings
+/// become default values, so one mirror can't break the whole [Posts]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Post {
     /// picture in miniature
+    #[serde(deserialize_with = "lenient::string")]
     pub preview_url: String,
     /// picture resized by default
     /// W - `sample_wight`
     /// H - `sample_hight`
+    #[serde(deserialize_with = "lenient::string")]
     pub sample_url: String,
     /// raw picture
     /// W - `wight`
     /// H - `hight`
+    #[serde(deserialize_with = "lenient::string")]
     pub file_url: String,
+    /// Directory of files on cdn
+    #[serde(deserialize_with = "lenient::number")]
+    pub directory: i64,
     /// Hash of picture
+    #[serde(deserialize_with = "lenient::string")]
     pub hash: String,
     /// W of raw picture (`file_url`)
+    #[serde(deserialize_with = "lenient::number")]
     pub width: i64,
     /// H of raw picture (`file_url`)
+    #[serde(deserialize_with = "lenient::number")]
     pub height: i64,
     /// Unique ID
+    #[serde(deserialize_with = "lenient::number")]
     pub id: i64,
     /// File name
+    #[serde(deserialize_with = "lenient::string")]
     pub image: String,
+    /// Time of last change
+    #[serde(deserialize_with = "lenient::date_time")]
+    pub change: Option<DateTime>,
+    /// Time of upload, only some mirrors send it
+    #[serde(
+        deserialize_with = "lenient::date_time",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub created_at: Option<DateTime>,
     /// Author
+    #[serde(deserialize_with = "lenient::string")]
     pub owner: String,
     /// Linked ID
+    #[serde(deserialize_with = "lenient::number")]
     pub parent_id: i64,
+    /// Rating, [None] if it's unknown
+    #[serde(deserialize_with = "lenient::rating")]
+    pub rating: Option<Rating>,
     /// has resized ?
+    #[serde(deserialize_with = "lenient::boolean")]
     pub sample: bool,
     /// H of `sample_picture`
+    #[serde(deserialize_with = "lenient::number")]
     pub sample_height: i64,
     /// W of `sample_picture`
+    #[serde(deserialize_with = "lenient::number")]
     pub sample_width: i64,
     /// Home many people rate this image?
+    #[serde(deserialize_with = "lenient::number")]
     pub score: i64,
     /// Tags of picture
+    #[serde(deserialize_with = "lenient::string")]
     pub tags: String,
+    /// Where picture comes from, often url
+    #[serde(deserialize_with = "lenient::string")]
+    pub source: String,
+    /// Status of moderation, like `active`
+    #[serde(deserialize_with = "lenient::string")]
+    pub status: String,
     /// Used for translation (in my case)
+    #[serde(deserialize_with = "lenient::boolean")]
     pub has_notes: bool,
-    // pub comment_count: i64,
+    /// Number of comments
+    #[serde(deserialize_with = "lenient::number")]
+    pub comment_count: i64,
+}
+
+impl Post {
+    /// Kind of `file_url`, see [MediaKind::detect]
+    #[inline]
+    pub fn media_kind(&self) -> MediaKind {
+        MediaKind::detect(&self.file_url, &self.tags)
+    }
+
+    /// Compare posts by field of `key` in ascending order
+    pub fn cmp_by(&self, other: &Post, key: SortKey) -> Ordering {
+        match key {
+            SortKey::Id => self.id.cmp(&other.id),
+            SortKey::Score => self.score.cmp(&other.score),
+            SortKey::Rating => self.rating.cmp(&other.rating),
+            SortKey::User => self.owner.cmp(&other.owner),
+            SortKey::Width => self.width.cmp(&other.width),
+            SortKey::Height => self.height.cmp(&other.height),
+            SortKey::Parent => self.parent_id.cmp(&other.parent_id),
+            SortKey::Source => self.source.cmp(&other.source),
+            SortKey::Updated => self.change.cmp(&other.change),
+        }
+    }
+}
+
+/// Tolerant decoding of [Post] fields
+mod lenient {
+    use serde::{Deserialize, Deserializer};
+    use serde_json::Value;
+
+    use super::{DateTime, Rating};
+
+    pub fn string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        Ok(match Value::deserialize(deserializer)? {
+            Value::String(x) => x,
+            Value::Null => String::new(),
+            x => x.to_string(),
+        })
+    }
+
+    pub fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        Ok(match Value::deserialize(deserializer)? {
+            Value::Number(x) => x
+                .as_i64()
+                .or_else(|| x.as_f64().map(|x| x as i64))
+                .unwrap_or_default(),
+            Value::String(x) => x.trim().parse().unwrap_or_default(),
+            Value::Bool(x) => x as i64,
+            _ => 0,
+        })
+    }
+
+    pub fn boolean<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        Ok(match Value::deserialize(deserializer)? {
+            Value::Bool(x) => x,
+            Value::Number(x) => x.as_i64().is_some_and(|x| x != 0),
+            Value::String(x) => matches!(x.trim(), "true" | "1"),
+            _ => false,
+        })
+    }
+
+    pub fn rating<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Rating>, D::Error> {
+        Ok(match Value::deserialize(deserializer)? {
+            Value::String(x) => x.parse().ok(),
+            _ => None,
+        })
+    }
+
+    pub fn date_time<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime>, D::Error> {
+        Ok(DateTime::deserialize(Value::deserialize(deserializer)?).ok())
+    }
 }
 
 impl From<Vec<Post>> for Posts {
@@ -68,7 +192,67 @@ impl From<()> for Posts {
     }
 }
 
+impl FromIterator<Post> for Posts {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Post>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Post> for Posts {
+    #[inline]
+    fn extend<T: IntoIterator<Item = Post>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for Posts {
+    type Item = Post;
+    type IntoIter = std::vec::IntoIter<Post>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Posts {
+    type Item = &'a Post;
+    type IntoIter = std::slice::Iter<'a, Post>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Index<usize> for Posts {
+    type Output = Post;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
 impl Posts {
+    /// Decode [Posts] from json body of api
+    ///
+    /// Empty body is empty [Posts], api sends it when nothing is found
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert!(Posts::from_json(b"").unwrap().is_empty());
+    /// assert!(Posts::from_json(b"[]").unwrap().is_empty());
+    /// ```
+    pub fn from_json(body: &[u8]) -> crate::error::Result<Self> {
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(body)?)
+    }
+
     /// get `preview_url` of all posts
     ///
     /// ```
@@ -124,7 +308,7 @@ impl Posts {
     /// }
     /// ```
     #[inline]
-    pub fn get_urls_ext(&self) -> MiniPosts {
+    pub fn get_urls_ext(&self) -> MiniPosts<'_> {
         self.into()
     }
 
@@ -138,7 +322,7 @@ impl Posts {
     /// }
     /// ```
     #[inline]
-    pub fn get_url_ext(&self) -> Option<MiniPost> {
+    pub fn get_url_ext(&self) -> Option<MiniPost<'_>> {
         self.0.first().map(|x| x.into())
     }
 
@@ -182,6 +366,143 @@ impl Posts {
         self.0.first().map(|x| x.file_url.as_str())
     }
 
+    /// Keep only posts of `kind`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn dwl() {
+    ///     let binding = R34Params::init().limit(10).download().await.unwrap();
+    ///     let images = binding.filter_media(MediaKind::Image);
+    /// }
+    /// ```
+    #[inline]
+    pub fn filter_media(&self, kind: MediaKind) -> Posts {
+        self.filter(|x| x.media_kind() == kind)
+    }
+
+    /// Iterate over posts
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Post> {
+        self.0.iter()
+    }
+
+    /// Copy posts which match `predicate`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn dwl() {
+    ///     let binding = R34Params::init().limit(10).download().await.unwrap();
+    ///     let popular = binding.filter(|x| x.score >= 100);
+    /// }
+    /// ```
+    pub fn filter(&self, mut predicate: impl FnMut(&Post) -> bool) -> Posts {
+        self.0.iter().filter(|x| predicate(x)).cloned().collect()
+    }
+
+    /// Copy posts which aren't blocked by [Blacklist]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn dwl() {
+    ///     let blacklist: Blacklist = "ai_generated\nscore:<0".parse().unwrap();
+    ///     let binding = R34Params::init().limit(10).download().await.unwrap();
+    ///     let allowed = binding.apply_blacklist(&blacklist);
+    /// }
+    /// ```
+    #[inline]
+    pub fn apply_blacklist(&self, blacklist: &Blacklist) -> Posts {
+        self.filter(|x| blacklist.allows(x))
+    }
+
+    /// Keep only posts which match `predicate`
+    #[inline]
+    pub fn retain(&mut self, predicate: impl FnMut(&Post) -> bool) {
+        self.0.retain(predicate)
+    }
+
+    /// Sort by many keys, next key is used when posts are equal by previous one
+    ///
+    /// Sorting is stable, so posts which are equal by all keys keep their order
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let mut posts: Posts = (1..=3)
+    ///     .map(|id| Post { id, score: id % 2, ..Default::default() })
+    ///     .collect();
+    /// posts.sort_by([(SortKey::Score, SortOrder::Desc), (SortKey::Id, SortOrder::Asc)]);
+    /// let ids: Vec<i64> = posts.iter().map(|x| x.id).collect();
+    /// assert_eq!(ids, vec![1, 3, 2]);
+    /// ```
+    pub fn sort_by(&mut self, keys: impl IntoIterator<Item = (SortKey, SortOrder)>) {
+        let keys: Vec<(SortKey, SortOrder)> = keys.into_iter().collect();
+        self.0.sort_by(|a, b| {
+            keys.iter()
+                .map(|(key, order)| match order {
+                    SortOrder::Asc => a.cmp_by(b, *key),
+                    SortOrder::Desc => b.cmp_by(a, *key),
+                })
+                .find(|x| x.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+    }
+
+    /// Remove posts with the same `id`, the first one is kept
+    pub fn dedup_by_id(&mut self) {
+        let mut seen = HashSet::new();
+        self.0.retain(|x| seen.insert(x.id))
+    }
+
+    /// Remove posts with the same `hash`, the first one is kept
+    ///
+    /// Posts without `hash` are never removed
+    pub fn dedup_by_hash(&mut self) {
+        let mut seen = HashSet::new();
+        self.0
+            .retain(|x| x.hash.is_empty() || seen.insert(x.hash.clone()))
+    }
+
+    /// Posts of both lists by `id`, posts of `self` go first
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let posts = |ids: &[i64]| -> Posts {
+    ///     ids.iter().map(|&id| Post { id, ..Default::default() }).collect()
+    /// };
+    /// let ids = |posts: Posts| -> Vec<i64> { posts.iter().map(|x| x.id).collect() };
+    /// let (a, b) = (posts(&[1, 2, 3]), posts(&[3, 4]));
+    ///
+    /// assert_eq!(ids(a.union(&b)), vec![1, 2, 3, 4]);
+    /// assert_eq!(ids(a.intersection(&b)), vec![3]);
+    /// assert_eq!(ids(a.difference(&b)), vec![1, 2]);
+    /// ```
+    pub fn union(&self, other: &Posts) -> Posts {
+        let mut posts = self.clone();
+        posts.extend(other.0.iter().cloned());
+        posts.dedup_by_id();
+        posts
+    }
+
+    /// Posts of `self` which are in `other` by `id`
+    pub fn intersection(&self, other: &Posts) -> Posts {
+        let ids = other.ids();
+        self.filter(|x| ids.contains(&x.id))
+    }
+
+    /// Posts of `self` which aren't in `other` by `id`
+    pub fn difference(&self, other: &Posts) -> Posts {
+        let ids = other.ids();
+        self.filter(|x| !ids.contains(&x.id))
+    }
+
+    fn ids(&self) -> HashSet<i64> {
+        self.0.iter().map(|x| x.id).collect()
+    }
+
     /// Make ref from [Posts]
     #[inline]
     pub fn data_ref(&self) -> &Vec<Post> {
@@ -316,9 +637,106 @@ mod tests {
     use uller::JsonDownload;
 
     use crate::prelude::*;
+    use crate::testing::FakeBooru;
+
+    /// Global client is set once, tests run in parallel
+    fn fake() {
+        static FAKE: std::sync::Once = std::sync::Once::new();
+        FAKE.call_once(|| {
+            let booru = FakeBooru::init()
+                .json(include_bytes!("../../../tests/fixtures/posts.json"))
+                .unwrap()
+                .generate(50);
+            R34Client::set_global(booru.client());
+        });
+    }
+
+    #[test]
+    fn media() {
+        let posts = Posts::from_json(include_bytes!("../../../tests/fixtures/posts.json")).unwrap();
+        let kinds: Vec<MediaKind> = posts.data_ref().iter().map(Post::media_kind).collect();
+        assert_eq!(
+            kinds,
+            vec![MediaKind::Image, MediaKind::Animated, MediaKind::Video]
+        );
+        let videos = posts.filter_media(MediaKind::Video);
+        assert_eq!(videos.len(), 1);
+        assert_eq!(videos.data_ref()[0].id, 10542350);
+        let mini = posts.get_urls_ext().filter_media(MediaKind::Animated);
+        assert_eq!(mini.as_ref()[0].id(), 10542300);
+    }
+
+    #[test]
+    fn collection() {
+        let post = |id: i64, hash: &str, width: i64| Post {
+            id,
+            hash: hash.to_string(),
+            width,
+            ..Default::default()
+        };
+        let mut posts: Posts = vec![post(1, "a", 100), post(2, "b", 200)]
+            .into_iter()
+            .collect();
+        posts.extend([post(3, "a", 200), post(2, "c", 300), post(4, "", 100)]);
+        posts.extend([post(5, "", 100)]);
+        assert_eq!(posts[2].id, 3);
+
+        let mut by_id = posts.clone();
+        by_id.dedup_by_id();
+        assert_eq!(by_id.len(), 5);
+        let mut by_hash = posts.clone();
+        by_hash.dedup_by_hash();
+        assert_eq!(by_hash.len(), 5);
+        assert!(by_hash.iter().all(|x| x.id != 3));
+
+        by_id.sort_by([
+            (SortKey::Width, SortOrder::Desc),
+            (SortKey::Id, SortOrder::Desc),
+        ]);
+        let ids: Vec<i64> = by_id.into_iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![3, 2, 5, 4, 1]);
+
+        posts.retain(|x| x.width > 100);
+        assert_eq!(posts.len(), 3);
+        assert_eq!((&posts).into_iter().filter(|x| x.id == 2).count(), 2);
+    }
+
+    #[test]
+    fn decode() {
+        let posts = Posts::from_json(include_bytes!("../../../tests/fixtures/posts.json")).unwrap();
+        let post = &posts.data_ref()[0];
+        assert_eq!(post.rating, Some(Rating::Safe));
+        assert_eq!(post.directory, 2413);
+        assert_eq!(post.comment_count, 3);
+        assert_eq!(post.status, "active");
+        assert_eq!(post.change.unwrap().to_string(), "2024-06-10T06:13:20Z");
+
+        // other mirror
+        let json = r#"[{
+            "id": "5",
+            "score": null,
+            "rating": "e",
+            "sample": 1,
+            "tags": "dark",
+            "created_at": "Sat Jul 06 12:34:56 +0000 2024",
+            "change": "never"
+        }]"#;
+        let posts = Posts::from_json(json.as_bytes()).unwrap();
+        let post = &posts.data_ref()[0];
+        assert_eq!(post.id, 5);
+        assert_eq!(post.score, 0);
+        assert_eq!(post.rating, Some(Rating::Explicit));
+        assert!(post.sample);
+        assert_eq!(post.change, None);
+        assert_eq!(post.created_at.unwrap().unix(), 1720269296);
+
+        let json = serde_json::to_string(&posts).unwrap();
+        assert_eq!(Posts::from_json(json.as_bytes()).unwrap(), posts);
+    }
 
     #[tokio::test]
     async fn create_make_link_search_with_id() {
+        fake();
         //fishey fishey
         let binding = R34Params::init().id(10542274).download().await.unwrap();
         let result = binding.get_f_urls();
@@ -327,6 +745,7 @@ mod tests {
     }
     #[tokio::test]
     async fn check_error() {
+        fake();
         let binding = R34Params::init().limit(10).download().await.unwrap();
         let result = binding.get_f_urls();
         println!("{:#?}", result);
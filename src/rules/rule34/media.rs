@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use super::query::Query;
+
+/// Kind of file of post
+///
+/// Detected by extension of `file_url`, tags `animated` and `video`
+/// are used only when extension can't tell it
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// assert_eq!(
+///     MediaKind::detect("https://cdn/images/1/abc.mp4?123", ""),
+///     MediaKind::Video
+/// );
+/// assert_eq!(
+///     MediaKind::detect("https://cdn/images/1/abc.png", "dark animated"),
+///     MediaKind::Animated
+/// );
+/// assert_eq!(MediaKind::detect("https://cdn/images/1/abc.jpeg", "dark"), MediaKind::Image);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MediaKind {
+    /// Still picture, like `jpg` or `png`
+    Image,
+    /// Animated picture, like `gif`
+    Animated,
+    /// Video, like `webm` or `mp4`
+    Video,
+}
+
+impl MediaKind {
+    /// Tag of animated posts
+    pub const ANIMATED_TAG: &'static str = "animated";
+    /// Tag of video posts
+    pub const VIDEO_TAG: &'static str = "video";
+
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Animated => "animated",
+            Self::Video => "video",
+        }
+    }
+
+    /// Kind of extension like `webm`, [None] if it's unknown
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" | "png" | "webp" | "bmp" | "avif" => Some(Self::Image),
+            "gif" | "apng" => Some(Self::Animated),
+            "webm" | "mp4" | "m4v" | "mov" | "mkv" => Some(Self::Video),
+            _ => None,
+        }
+    }
+
+    /// Detect kind of file by `url` and space separated `tags`
+    ///
+    /// `png`, `webp` and `avif` can be animated, so tag `animated` makes them
+    /// [MediaKind::Animated], other images stay [MediaKind::Image]
+    pub fn detect(url: &str, tags: &str) -> Self {
+        let has_tag = |tag: &str| tags.split_whitespace().any(|x| x == tag);
+        let animated = has_tag(Self::ANIMATED_TAG);
+        let extension = extension(url);
+        let can_animate = ["png", "webp", "avif"]
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(extension));
+        match Self::from_extension(extension) {
+            Some(Self::Image) if animated && can_animate => Self::Animated,
+            Some(kind) => kind,
+            None if has_tag(Self::VIDEO_TAG) => Self::Video,
+            None if animated => Self::Animated,
+            None => Self::Image,
+        }
+    }
+
+    /// Query which finds only this kind, by tags `animated` and `video`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert_eq!(MediaKind::Image.query().to_string(), "-animated -video");
+    /// assert_eq!(MediaKind::Animated.query().to_string(), "animated -video");
+    /// assert_eq!(MediaKind::Video.query().to_string(), "video");
+    /// ```
+    pub fn query(&self) -> Query {
+        let animated = Query::tag(Self::ANIMATED_TAG);
+        let video = Query::tag(Self::VIDEO_TAG);
+        match self {
+            Self::Image => (!animated).and(!video),
+            Self::Animated => animated.and(!video),
+            Self::Video => video,
+        }
+    }
+
+    /// Query which drops this kind
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert_eq!(MediaKind::Video.exclude().to_string(), "-video");
+    /// ```
+    pub fn exclude(&self) -> Query {
+        match self {
+            Self::Image => Query::tag(Self::ANIMATED_TAG).or(Query::tag(Self::VIDEO_TAG)),
+            Self::Animated => !Query::tag(Self::ANIMATED_TAG),
+            Self::Video => !Query::tag(Self::VIDEO_TAG),
+        }
+    }
+}
+
+impl Display for MediaKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Extension of file in `url` without query and fragment
+pub(crate) fn extension(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    match name.rsplit_once('.') {
+        Some((_, extension)) => extension,
+        None => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        for (url, tags, kind) in [
+            ("https://cdn/images/1/a.jpg", "video", MediaKind::Image),
+            ("https://cdn/images/1/a.JPEG?1", "", MediaKind::Image),
+            ("https://cdn/images/1/a.jpg", "animated", MediaKind::Image),
+            (
+                "https://cdn/images/1/a.PNG",
+                "animated",
+                MediaKind::Animated,
+            ),
+            ("https://cdn/images/1/a.gif", "", MediaKind::Animated),
+            (
+                "https://cdn/images/1/a.webp",
+                "animated",
+                MediaKind::Animated,
+            ),
+            ("https://cdn/images/1/a.webm#t=1", "", MediaKind::Video),
+            ("https://cdn/images/1/a.mp4", "animated", MediaKind::Video),
+            (
+                "https://cdn/images/1/file",
+                "animated video",
+                MediaKind::Video,
+            ),
+            (
+                "https://cdn/images/1.2/file",
+                "animated",
+                MediaKind::Animated,
+            ),
+            ("", "", MediaKind::Image),
+        ] {
+            assert_eq!(MediaKind::detect(url, tags), kind, "{} {}", url, tags);
+        }
+    }
+
+    #[test]
+    fn query() {
+        assert_eq!(
+            MediaKind::Image.exclude().to_string(),
+            "( animated ~ video )"
+        );
+        assert_eq!(MediaKind::Animated.exclude().to_string(), "-animated");
+    }
+}
@@ -0,0 +1,617 @@
+use std::fmt::Display;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::data::Post;
+use super::query::Query;
+
+/// Rating of post
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rating {
+    /// `rating:safe`
+    Safe,
+    /// `rating:questionable`
+    Questionable,
+    /// `rating:explicit`
+    Explicit,
+}
+
+impl Rating {
+    /// Name of rating in `Rule34` syntax
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Safe => "safe",
+            Rating::Questionable => "questionable",
+            Rating::Explicit => "explicit",
+        }
+    }
+}
+
+impl Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Rating {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Rating {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Rating {
+    type Err = MetaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "s" | "safe" => Ok(Rating::Safe),
+            "q" | "questionable" => Ok(Rating::Questionable),
+            "e" | "explicit" => Ok(Rating::Explicit),
+            _ => Err(MetaError::Unknown(format!("rating:{}", s))),
+        }
+    }
+}
+
+/// Order of sorting
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    /// From lower to higher
+    Asc,
+    /// From higher to lower
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    /// Name of order in `Rule34` syntax
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Field to sort by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    Id,
+    Score,
+    Rating,
+    User,
+    Width,
+    Height,
+    Parent,
+    Source,
+    Updated,
+}
+
+impl SortKey {
+    /// Name of key in `Rule34` syntax
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Id => "id",
+            SortKey::Score => "score",
+            SortKey::Rating => "rating",
+            SortKey::User => "user",
+            SortKey::Width => "width",
+            SortKey::Height => "height",
+            SortKey::Parent => "parent",
+            SortKey::Source => "source",
+            SortKey::Updated => "updated",
+        }
+    }
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = MetaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(SortKey::Id),
+            "score" => Ok(SortKey::Score),
+            "rating" => Ok(SortKey::Rating),
+            "user" => Ok(SortKey::User),
+            "width" => Ok(SortKey::Width),
+            "height" => Ok(SortKey::Height),
+            "parent" => Ok(SortKey::Parent),
+            "source" => Ok(SortKey::Source),
+            "updated" => Ok(SortKey::Updated),
+            _ => Err(MetaError::Unknown(format!("sort:{}", s))),
+        }
+    }
+}
+
+/// Comparison of numeric metatag
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let score = MetaTag::score(Compare::Ge(50)).unwrap();
+/// assert_eq!(Query::from(score).to_string(), "score:>=50");
+///
+/// let width = MetaTag::width(Compare::range(1920..=3840).unwrap()).unwrap();
+/// assert_eq!(Query::from(width).to_string(), "width:>=1920 width:<=3840");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compare {
+    /// `key:value`
+    Eq(i64),
+    /// `key:<value`
+    Lt(i64),
+    /// `key:<=value`
+    Le(i64),
+    /// `key:>value`
+    Gt(i64),
+    /// `key:>=value`
+    Ge(i64),
+    /// `key:>=min key:<=max`
+    Between(i64, i64),
+}
+
+impl Compare {
+    /// Make comparison from any range of [i64]
+    ///
+    /// Returns [MetaError::InvalidRange] if range is empty
+    pub fn range(range: impl RangeBounds<i64>) -> Result<Self, MetaError> {
+        let min = match range.start_bound() {
+            Bound::Included(x) => Some(*x),
+            Bound::Excluded(x) => Some(x.saturating_add(1)),
+            Bound::Unbounded => None,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(x) => Some(*x),
+            Bound::Excluded(x) => Some(x.saturating_sub(1)),
+            Bound::Unbounded => None,
+        };
+        let compare = match (min, max) {
+            (Some(min), Some(max)) if min == max => Compare::Eq(min),
+            (Some(min), Some(max)) => Compare::Between(min, max),
+            (Some(min), None) => Compare::Ge(min),
+            (None, Some(max)) => Compare::Le(max),
+            (None, None) => return Err(MetaError::InvalidRange(i64::MIN, i64::MAX)),
+        };
+        compare.check()?;
+        Ok(compare)
+    }
+
+    /// Lowest value which satisfies comparison
+    fn min(&self) -> Option<i64> {
+        match self {
+            Compare::Eq(x) | Compare::Ge(x) | Compare::Between(x, _) => Some(*x),
+            Compare::Gt(x) => Some(x.saturating_add(1)),
+            Compare::Lt(_) | Compare::Le(_) => None,
+        }
+    }
+
+    /// Highest value which satisfies comparison
+    fn max(&self) -> Option<i64> {
+        match self {
+            Compare::Eq(x) | Compare::Le(x) | Compare::Between(_, x) => Some(*x),
+            Compare::Lt(x) => Some(x.saturating_sub(1)),
+            Compare::Gt(_) | Compare::Ge(_) => None,
+        }
+    }
+
+    /// Check if any value satisfies comparison
+    fn check(&self) -> Result<(), MetaError> {
+        match self {
+            Compare::Between(min, max) if min > max => Err(MetaError::InvalidRange(*min, *max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check if `value` satisfies comparison
+    pub fn matches(&self, value: i64) -> bool {
+        match *self {
+            Compare::Eq(x) => value == x,
+            Compare::Lt(x) => value < x,
+            Compare::Le(x) => value <= x,
+            Compare::Gt(x) => value > x,
+            Compare::Ge(x) => value >= x,
+            Compare::Between(min, max) => (min..=max).contains(&value),
+        }
+    }
+
+    fn values(&self) -> Vec<String> {
+        match self {
+            Compare::Eq(x) => vec![x.to_string()],
+            Compare::Lt(x) => vec![format!("<{}", x)],
+            Compare::Le(x) => vec![format!("<={}", x)],
+            Compare::Gt(x) => vec![format!(">{}", x)],
+            Compare::Ge(x) => vec![format!(">={}", x)],
+            Compare::Between(min, max) => vec![format!(">={}", min), format!("<={}", max)],
+        }
+    }
+}
+
+impl FromStr for Compare {
+    type Err = MetaError;
+
+    /// Parse value of metatag like `>=50`, `<10` or `50`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |x: &str| {
+            x.parse::<i64>()
+                .map_err(|_| MetaError::Unknown(s.to_string()))
+        };
+        let compare = if let Some(x) = s.strip_prefix(">=") {
+            Compare::Ge(parse(x)?)
+        } else if let Some(x) = s.strip_prefix("<=") {
+            Compare::Le(parse(x)?)
+        } else if let Some(x) = s.strip_prefix('>') {
+            Compare::Gt(parse(x)?)
+        } else if let Some(x) = s.strip_prefix('<') {
+            Compare::Lt(parse(x)?)
+        } else {
+            Compare::Eq(parse(s)?)
+        };
+        Ok(compare)
+    }
+}
+
+/// Typed metatag of `Rule34`
+///
+/// Every constructor checks value, so invalid metatag can't be built,
+/// variants with checked values can be matched but not built outside of shuller
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let params = R34Params::init()
+///     .positive_tags(vec!["dark"])
+///     .meta(MetaTag::score(Compare::Ge(50)).unwrap())
+///     .meta(MetaTag::rating(Rating::Safe))
+///     .meta(MetaTag::sort(SortKey::Score, SortOrder::Desc));
+///
+/// assert_eq!(params.tags(), "dark score:>=50 rating:safe sort:score:desc");
+///
+/// assert!(MetaTag::md5("not a hash").is_err());
+/// assert!(MetaTag::width(Compare::Between(10, 5)).is_err());
+/// assert!(MetaTag::width(Compare::Le(-1)).is_err());
+/// ```
+///
+/// ```compile_fail
+/// use shuller::prelude::*;
+///
+/// let width = MetaTag::Width(Compare::Eq(-3));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MetaTag {
+    /// `score:`
+    #[non_exhaustive]
+    Score(Compare),
+    /// `width:`
+    #[non_exhaustive]
+    Width(Compare),
+    /// `height:`
+    #[non_exhaustive]
+    Height(Compare),
+    /// `id:`
+    #[non_exhaustive]
+    Id(Compare),
+    /// `rating:`
+    Rating(Rating),
+    /// `sort:key:order`
+    Sort(SortKey, SortOrder),
+    /// `md5:`
+    #[non_exhaustive]
+    Md5(String),
+    /// `user:`
+    #[non_exhaustive]
+    User(String),
+    /// `parent:`
+    Parent(u64),
+}
+
+impl MetaTag {
+    /// `score:`
+    pub fn score(compare: Compare) -> Result<Self, MetaError> {
+        compare.check()?;
+        Ok(MetaTag::Score(compare))
+    }
+    /// `width:`, can't be negative
+    pub fn width(compare: Compare) -> Result<Self, MetaError> {
+        Self::positive("width", compare)?;
+        Ok(MetaTag::Width(compare))
+    }
+    /// `height:`, can't be negative
+    pub fn height(compare: Compare) -> Result<Self, MetaError> {
+        Self::positive("height", compare)?;
+        Ok(MetaTag::Height(compare))
+    }
+    /// `id:`, can't be negative
+    pub fn id(compare: Compare) -> Result<Self, MetaError> {
+        Self::positive("id", compare)?;
+        Ok(MetaTag::Id(compare))
+    }
+    /// `rating:`
+    #[inline]
+    pub fn rating(rating: Rating) -> Self {
+        MetaTag::Rating(rating)
+    }
+    /// `sort:key:order`
+    #[inline]
+    pub fn sort(key: SortKey, order: SortOrder) -> Self {
+        MetaTag::Sort(key, order)
+    }
+    /// `md5:`, must be 32 hex chars
+    pub fn md5(hash: impl Into<String>) -> Result<Self, MetaError> {
+        let hash: String = hash.into();
+        if hash.len() != 32 || !hash.chars().all(|x| x.is_ascii_hexdigit()) {
+            return Err(MetaError::InvalidMd5(hash));
+        }
+        Ok(MetaTag::Md5(hash.to_ascii_lowercase()))
+    }
+    /// `user:`, can't be empty or contain whitespaces
+    pub fn user(name: impl Into<String>) -> Result<Self, MetaError> {
+        let name: String = name.into();
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(MetaError::InvalidUser(name));
+        }
+        Ok(MetaTag::User(name))
+    }
+    /// `parent:`
+    #[inline]
+    pub fn parent(id: u64) -> Self {
+        MetaTag::Parent(id)
+    }
+
+    /// Name of metatag in `Rule34` syntax
+    pub fn key(&self) -> &'static str {
+        match self {
+            MetaTag::Score(_) => "score",
+            MetaTag::Width(_) => "width",
+            MetaTag::Height(_) => "height",
+            MetaTag::Id(_) => "id",
+            MetaTag::Rating(_) => "rating",
+            MetaTag::Sort(_, _) => "sort",
+            MetaTag::Md5(_) => "md5",
+            MetaTag::User(_) => "user",
+            MetaTag::Parent(_) => "parent",
+        }
+    }
+
+    /// Check if `key` is name of metatag which can be parsed by [MetaTag::parse]
+    #[inline]
+    pub fn is_known(key: &str) -> bool {
+        matches!(
+            key,
+            "score" | "width" | "height" | "id" | "rating" | "sort" | "md5" | "user" | "parent"
+        )
+    }
+
+    fn positive(key: &'static str, compare: Compare) -> Result<(), MetaError> {
+        compare.check()?;
+        match (compare.min(), compare.max()) {
+            (Some(x), _) | (_, Some(x)) if x < 0 => Err(MetaError::Negative(key, x)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Parse metatag from `key` and `value`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert_eq!(
+    ///     MetaTag::parse("score", ">10").unwrap(),
+    ///     MetaTag::score(Compare::Gt(10)).unwrap()
+    /// );
+    /// ```
+    pub fn parse(key: &str, value: &str) -> Result<Self, MetaError> {
+        match key {
+            "score" => Self::score(value.parse()?),
+            "width" => Self::width(value.parse()?),
+            "height" => Self::height(value.parse()?),
+            "id" => Self::id(value.parse()?),
+            "rating" => Ok(Self::rating(value.parse()?)),
+            "sort" => {
+                let (key, order) = match value.split_once(':') {
+                    Some((key, "asc")) => (key, SortOrder::Asc),
+                    Some((key, "desc")) => (key, SortOrder::Desc),
+                    Some(_) => return Err(MetaError::Unknown(format!("sort:{}", value))),
+                    None => (value, SortOrder::default()),
+                };
+                Ok(Self::sort(key.parse()?, order))
+            }
+            "md5" => Self::md5(value),
+            "user" => Self::user(value),
+            "parent" => value
+                .parse()
+                .map(Self::parent)
+                .map_err(|_| MetaError::Unknown(format!("parent:{}", value))),
+            _ => Err(MetaError::Unknown(format!("{}:{}", key, value))),
+        }
+    }
+}
+
+impl MetaTag {
+    /// Check if `post` matches metatag
+    ///
+    /// [MetaTag::Sort] matches every post
+    pub fn matches(&self, post: &Post) -> bool {
+        match self {
+            MetaTag::Score(x) => x.matches(post.score),
+            MetaTag::Width(x) => x.matches(post.width),
+            MetaTag::Height(x) => x.matches(post.height),
+            MetaTag::Id(x) => x.matches(post.id),
+            MetaTag::Rating(x) => post.rating == Some(*x),
+            MetaTag::Sort(_, _) => true,
+            MetaTag::Md5(x) => post.hash.eq_ignore_ascii_case(x),
+            MetaTag::User(x) => post.owner.eq_ignore_ascii_case(x),
+            MetaTag::Parent(x) => post.parent_id == *x as i64,
+        }
+    }
+}
+
+impl From<MetaTag> for Query {
+    fn from(value: MetaTag) -> Self {
+        let key = value.key();
+        let values = match value {
+            MetaTag::Score(x) | MetaTag::Width(x) | MetaTag::Height(x) | MetaTag::Id(x) => {
+                x.values()
+            }
+            MetaTag::Rating(x) => vec![x.to_string()],
+            MetaTag::Sort(x, order) => vec![format!("{}:{}", x, order)],
+            MetaTag::Md5(x) | MetaTag::User(x) => vec![x],
+            MetaTag::Parent(x) => vec![x.to_string()],
+        };
+        Query::all(values.into_iter().map(|x| Query::meta(key, x))).unwrap_or(Query::And(vec![]))
+    }
+}
+
+/// Error of building [MetaTag]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaError {
+    /// Range where min > max
+    InvalidRange(i64, i64),
+    /// Negative value for field which can't be negative
+    Negative(&'static str, i64),
+    /// Md5 must be 32 hex chars
+    InvalidMd5(String),
+    /// User name can't be empty or contain whitespaces
+    InvalidUser(String),
+    /// Metatag or value isn't known
+    Unknown(String),
+}
+
+impl Display for MetaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetaError::InvalidRange(min, max) => write!(f, "invalid range: {}..={}", min, max),
+            MetaError::Negative(key, value) => {
+                write!(f, "{} can't be negative, got: {}", key, value)
+            }
+            MetaError::InvalidMd5(x) => write!(f, "invalid md5: {:?}", x),
+            MetaError::InvalidUser(x) => write!(f, "invalid user: {:?}", x),
+            MetaError::Unknown(x) => write!(f, "unknown metatag: {:?}", x),
+        }
+    }
+}
+
+impl std::error::Error for MetaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(tag: MetaTag) -> String {
+        Query::from(tag).to_string()
+    }
+
+    #[test]
+    fn compare() {
+        assert_eq!(render(MetaTag::score(Compare::Eq(5)).unwrap()), "score:5");
+        assert_eq!(render(MetaTag::score(Compare::Lt(5)).unwrap()), "score:<5");
+        assert_eq!(
+            render(MetaTag::score(Compare::Le(-5)).unwrap()),
+            "score:<=-5"
+        );
+        assert_eq!(render(MetaTag::id(Compare::Gt(5)).unwrap()), "id:>5");
+        assert_eq!(
+            render(MetaTag::height(Compare::Ge(5)).unwrap()),
+            "height:>=5"
+        );
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(Compare::range(1..10), Ok(Compare::Between(1, 9)));
+        assert_eq!(Compare::range(5..=5), Ok(Compare::Eq(5)));
+        assert_eq!(Compare::range(5..), Ok(Compare::Ge(5)));
+        assert_eq!(Compare::range(..5), Ok(Compare::Le(4)));
+        assert!(Compare::range(..).is_err());
+        assert!(Compare::range((Bound::Included(10), Bound::Included(5))).is_err());
+        assert_eq!(
+            render(MetaTag::id(Compare::range(100..=200).unwrap()).unwrap()),
+            "id:>=100 id:<=200"
+        );
+    }
+
+    #[test]
+    fn rejected() {
+        assert_eq!(
+            MetaTag::width(Compare::Ge(-1)),
+            Err(MetaError::Negative("width", -1))
+        );
+        assert_eq!(
+            MetaTag::height(Compare::Lt(-5)),
+            Err(MetaError::Negative("height", -6))
+        );
+        assert_eq!(
+            MetaTag::id(Compare::Le(-1)),
+            Err(MetaError::Negative("id", -1))
+        );
+        assert!(MetaTag::id(Compare::Lt(10)).is_ok());
+        assert_eq!(
+            MetaTag::score(Compare::Between(3, 1)),
+            Err(MetaError::InvalidRange(3, 1))
+        );
+        assert!(MetaTag::md5("abc").is_err());
+        assert!(MetaTag::user("two words").is_err());
+        assert!(MetaTag::user("").is_err());
+    }
+
+    #[test]
+    fn other() {
+        assert_eq!(render(MetaTag::rating(Rating::Explicit)), "rating:explicit");
+        assert_eq!(
+            render(MetaTag::sort(SortKey::Updated, SortOrder::Asc)),
+            "sort:updated:asc"
+        );
+        assert_eq!(
+            render(MetaTag::md5("D41D8CD98F00B204E9800998ECF8427E").unwrap()),
+            "md5:d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(render(MetaTag::user("fish").unwrap()), "user:fish");
+        assert_eq!(render(MetaTag::parent(10)), "parent:10");
+        assert_eq!(
+            (!Query::from(MetaTag::rating(Rating::Explicit))).to_string(),
+            "-rating:explicit"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            MetaTag::parse("sort", "score:asc"),
+            Ok(MetaTag::sort(SortKey::Score, SortOrder::Asc))
+        );
+        assert_eq!(
+            MetaTag::parse("rating", "safe"),
+            Ok(MetaTag::rating(Rating::Safe))
+        );
+        assert_eq!(
+            MetaTag::parse("width", ">=1920"),
+            MetaTag::width(Compare::Ge(1920))
+        );
+        assert!(MetaTag::parse("fish", "1").is_err());
+        assert!(MetaTag::parse("score", ">=a").is_err());
+    }
+}
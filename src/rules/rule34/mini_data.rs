@@ -1,6 +1,8 @@
 use std::fmt::Display;
 
+use super::blacklist::Blacklist;
 use super::data::{Post, Posts};
+use super::media::MediaKind;
 
 /// Less info struct of [Post]
 #[derive(Debug, Clone)]
@@ -16,6 +18,8 @@ pub struct MiniPost<'a> {
     height: u64,
     /// tags
     tags: &'a str,
+    /// full post
+    post: &'a Post,
 }
 
 impl<'a> MiniPost<'a> {
@@ -41,6 +45,18 @@ impl<'a> MiniPost<'a> {
     pub fn tags_vec(&self) -> Vec<&str> {
         self.tags.split(" ").collect()
     }
+
+    /// Full [Post] of this
+    #[inline]
+    pub fn post(&self) -> &'a Post {
+        self.post
+    }
+
+    /// Kind of `file_url`, see [MediaKind::detect]
+    #[inline]
+    pub fn media_kind(&self) -> MediaKind {
+        MediaKind::detect(self.file_url, self.tags)
+    }
 }
 
 impl<'a> From<MiniPosts<'a>> for Vec<String> {
@@ -57,6 +73,7 @@ impl<'a> From<&'a Post> for MiniPost<'a> {
             width: val.width as u64,
             height: val.height as u64,
             tags: &val.tags,
+            post: val,
         }
     }
 }
@@ -106,6 +123,26 @@ impl<'a> MiniPosts<'a> {
         self.0.is_empty()
     }
 
+    /// Keep only posts of `kind`
+    pub fn filter_media(&self, kind: MediaKind) -> MiniPosts<'a> {
+        self.0
+            .iter()
+            .filter(|x| x.media_kind() == kind)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Keep only posts which aren't blocked by [Blacklist]
+    pub fn apply_blacklist(&self, blacklist: &Blacklist) -> MiniPosts<'a> {
+        self.0
+            .iter()
+            .filter(|x| blacklist.allows(x.post))
+            .cloned()
+            .collect::<Vec<_>>()
+            .into()
+    }
+
     /// get `file_url` of all posts
     /// ```
     /// use shuller::prelude::*;
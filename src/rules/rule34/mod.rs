@@ -1,11 +1,33 @@
+/// Blacklist of tags checked on client side, see [blacklist::Blacklist]
+pub mod blacklist;
+/// Shared client for many params
+pub mod client;
+/// `api_key` and `user_id` for DAPI
+pub mod credentials;
 /// Picture structure
 ///
 /// Contain url's of cdn
 pub mod data;
+/// Kind of media of posts, see [media::MediaKind]
+pub mod media;
+/// Typed metatags like `score:>=50`
+pub mod meta;
 /// fit data of [Post]
 pub mod mini_data;
+/// Pagination of [params::R34Params]
+pub mod pages;
 /// Params for `Rule34` Api
 pub mod params;
+/// Typed tag query
+pub mod query;
+/// Allowed ratings of posts, see [safety::SafetyPolicy]
+pub mod safety;
+/// Types of tags like artist, see [tag_type::TagTypes]
+pub mod tag_type;
+/// Time of posts, see [time::DateTime]
+pub mod time;
+/// Errors of [params::R34Params::validate]
+pub mod validation;
 // pub use crate::rules::rule34::params::R34Params;
 // pub use crate::rules::rule34::data::Posts;
 /// Macros for fast creating rule34 params by using specific pattern
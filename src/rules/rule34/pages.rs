@@ -0,0 +1,140 @@
+use super::blacklist::Blacklist;
+use super::client::R34Client;
+use super::data::Posts;
+use super::params::{R34Params, R34ParamsOwned};
+use crate::error::Result;
+
+/// Pages of [Posts] one after another, see [R34Client::pages]
+///
+/// Ends after page which has less posts than `limit` (at most [R34Params::MAX_LIMIT]) or after error
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// async fn example() {
+///     let client = R34Client::init();
+///     let mut pages = client.pages(&R34Params::init().positive_tags(vec!["dark"]).limit(100));
+///     while let Some(posts) = pages.next().await {
+///         println!("{}", posts.unwrap().len());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pages {
+    client: R34Client,
+    params: R34ParamsOwned,
+    blacklist: Option<Blacklist>,
+    done: bool,
+}
+
+impl Pages {
+    #[inline]
+    pub(crate) fn new(client: R34Client, params: R34ParamsOwned) -> Self {
+        Self {
+            client,
+            params,
+            blacklist: None,
+            done: false,
+        }
+    }
+
+    /// Drop posts blocked by [Blacklist] from every page
+    ///
+    /// Pages can become shorter or empty, but they still end only by answer of api
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn example(client: &R34Client) {
+    ///     let blacklist: Blacklist = "ai_generated\nrating:explicit".parse().unwrap();
+    ///     let posts = client
+    ///         .pages(&R34Params::init().positive_tags(vec!["dark"]).limit(100))
+    ///         .blacklist(blacklist)
+    ///         .collect()
+    ///         .await;
+    /// }
+    /// ```
+    #[inline]
+    pub fn blacklist(mut self, blacklist: Blacklist) -> Self {
+        self.blacklist = Some(blacklist);
+        self
+    }
+
+    /// Client which downloads pages
+    #[cfg(feature = "download")]
+    #[inline]
+    pub(crate) fn client(&self) -> &R34Client {
+        &self.client
+    }
+
+    /// Download next page, [None] after the last one
+    pub async fn next(&mut self) -> Option<Result<Posts>> {
+        if self.done {
+            return None;
+        }
+        let result = self.client.download_page(&self.params).await;
+        match &result {
+            Ok((0, _)) => return None,
+            Ok((received, _))
+                if *received >= self.params.limit.min(R34Params::MAX_LIMIT) as usize =>
+            {
+                match self.params.page.checked_add(1) {
+                    Some(page) => self.params.page = page,
+                    None => self.done = true,
+                }
+            }
+            _ => self.done = true,
+        }
+        Some(result.map(|(_, mut posts)| {
+            if let Some(blacklist) = &self.blacklist {
+                posts.retain(|x| blacklist.allows(x));
+            }
+            posts
+        }))
+    }
+
+    /// Download all remaining pages into one [Posts]
+    pub async fn collect(mut self) -> Result<Posts> {
+        let mut all = vec![];
+        while let Some(posts) = self.next().await {
+            all.extend(posts?.data());
+        }
+        Ok(all.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::testing::FakeBooru;
+
+    #[tokio::test]
+    async fn filtered() {
+        let booru = FakeBooru::init().generate(40);
+        let blacklist: Blacklist = "dark".parse().unwrap();
+        let client = booru.client().safety(SafetyPolicy::safe());
+        let params = R34Params::init().limit(5).page(0);
+        let all = booru.client().pages(&params).collect().await.unwrap();
+        assert_eq!(all.len(), 40);
+
+        // pages with dropped posts don't end iteration
+        let posts = client
+            .pages(&params)
+            .blacklist(blacklist.clone())
+            .collect()
+            .await
+            .unwrap();
+        let expected = all.filter(|x| x.rating == Some(Rating::Safe) && blacklist.allows(x));
+        assert!(!expected.is_empty());
+        assert_eq!(posts, expected);
+    }
+
+    #[tokio::test]
+    async fn lenient_limit() {
+        let booru = FakeBooru::init().generate(1500);
+        let params = R34Params::init().limit(5000).page(0).build_lenient();
+        let posts = booru.client().pages(&params).collect().await.unwrap();
+        assert_eq!(posts.len(), 1500);
+        assert_eq!(booru.requests().len(), 2);
+    }
+}
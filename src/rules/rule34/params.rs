@@ -1,10 +1,23 @@
 #[cfg(feature = "rand")]
 use crate::random_usize;
 
-use crate::rules::rule34::data::Posts;
+use crate::error::{Error, Result};
+use crate::rules::rule34::client::R34Client;
+use crate::rules::rule34::credentials::{redact_url, Credentials};
+use crate::rules::rule34::data::{Post, Posts};
+use crate::rules::rule34::media::MediaKind;
+use crate::rules::rule34::meta::MetaTag;
+use crate::rules::rule34::pages::Pages;
+use crate::rules::rule34::query::{ParseError, Query};
+use crate::rules::rule34::safety::SafetyPolicy;
+use crate::rules::rule34::validation::{check_tag, TagKind, ValidationError, ValidationErrors};
 use crate::tag_suppress;
 use crate::toggler;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::str::FromStr;
 use uller::{JsonDownload, MakeLink, Url};
 
 /// Rule 34 params
@@ -22,20 +35,30 @@ use uller::{JsonDownload, MakeLink, Url};
 ///     .url_generate();
 /// ```
 /// **instance** is { url: `https://api.rule34.xxx/index.php?page=dapi&s=post&q=index&tags=anime base sunglasses -ai_generated&json=1&limit=5&pid=2` }
-#[derive(PartialEq, Debug)]
+///
+/// Params can be stored in config or sent across tasks,
+/// use [R34Params::into_owned] to drop borrowed tags.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct R34Params<'a> {
     /// Default value for api
     // page
+    #[serde(skip)]
     p: &'static str,
     /// Default value for api
+    #[serde(skip)]
     s: &'static str,
     /// Default value for api
+    #[serde(skip)]
     q: &'static str,
     /// Positive tags
-    pub positive_tags: Vec<&'a str>,
+    pub positive_tags: Vec<Cow<'a, str>>,
     /// Negative tags (`"-"` sets automaticly)
-    pub negative_tags: Vec<&'a str>,
+    pub negative_tags: Vec<Cow<'a, str>>,
+    /// Typed query, rendered after positive and negative tags
+    pub query: Option<Query>,
     /// Default value for lib
+    #[serde(skip)]
     json: bool,
     /// Limit of links in response MAX: 1000
     /// By default = 1
@@ -46,44 +69,44 @@ pub struct R34Params<'a> {
     pub page: u16,
     // id of post
     pub id: Option<usize>,
+    /// `api_key` and `user_id`, never serialized
+    #[serde(skip)]
+    pub credentials: Option<Credentials>,
+    /// Url of api, by default = [R34Params::DEFAULT_BASE_URL]
+    pub base_url: Option<Cow<'a, str>>,
+    /// Allowed ratings
+    pub safety: Option<SafetyPolicy>,
+    /// Set by [R34Params::build_lenient], url is generated without [R34Params::validate]
+    #[serde(skip)]
+    lenient: bool,
 }
 
 impl MakeLink for R34Params<'_> {
+    /// Limit is clamped to [R34Params::MAX_LIMIT] and tags aren't validated,
+    /// use [R34Params::try_url_generate] to check them
+    ///
+    /// **Panics** if `base_url` isn't valid url
     fn url_generate(&self) -> Url {
-        let url = "https://api.rule34.xxx/index.php";
-        let tags = tag_suppress!(self.positive_tags, self.negative_tags);
-        if let Some(id) = self.id {
-            return Url::parse_with_params(
-                url,
-                &[
-                    ("page", self.p),
-                    ("s", self.s),
-                    ("q", self.q),
-                    ("tags", &tags),
-                    ("json", toggler!(self.json)),
-                    ("limit", self.limit.to_string().as_ref()),
-                    ("pid", self.page.to_string().as_ref()),
-                    ("id", id.to_string().as_ref()),
-                ],
-            )
-            .expect("Failed to parse URL with params");
-        }
-        Url::parse_with_params(
-            url,
-            &[
-                ("page", self.p),
-                ("s", self.s),
-                ("q", self.q),
-                ("tags", &tags),
-                ("json", toggler!(self.json)),
-                ("limit", self.limit.to_string().as_ref()),
-                ("pid", self.page.to_string().as_ref()),
-            ],
-        )
-        .expect("Failed to parse URL with params")
+        self.url_with(None, None)
+            .expect("Failed to parse URL with params")
     }
 }
 
+/// [R34Params] which owns all tags
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let tags = String::from("dark fish");
+/// let params: R34ParamsOwned = R34Params::init()
+///     .positive_tags(tags.split(' ').collect())
+///     .into_owned();
+/// drop(tags);
+///
+/// std::thread::spawn(move || println!("{}", params));
+/// ```
+pub type R34ParamsOwned = R34Params<'static>;
+
 impl Default for R34Params<'_> {
     fn default() -> Self {
         Self {
@@ -93,21 +116,90 @@ impl Default for R34Params<'_> {
             q: "index",
             positive_tags: vec![],
             negative_tags: vec![],
+            query: None,
             json: true,
             limit: 1,
             // pid
             page: 1,
             id: None,
+            credentials: None,
+            base_url: None,
+            safety: None,
+            lenient: false,
         }
     }
 }
 
 impl<'a> R34Params<'a> {
+    /// Max limit of links in one response
+    pub const MAX_LIMIT: u16 = 1000;
+    /// Url of `Rule34` api
+    pub const DEFAULT_BASE_URL: &'static str = "https://api.rule34.xxx/index.php";
+
     /// Init params
     #[inline]
     pub fn init() -> Self {
         Self::default()
     }
+    /// Copy borrowed tags, so params can live anywhere
+    pub fn into_owned(self) -> R34ParamsOwned {
+        let own = |tags: Vec<Cow<'a, str>>| -> Vec<Cow<'static, str>> {
+            tags.into_iter()
+                .map(|x| Cow::Owned(x.into_owned()))
+                .collect()
+        };
+        R34Params {
+            p: self.p,
+            s: self.s,
+            q: self.q,
+            positive_tags: own(self.positive_tags),
+            negative_tags: own(self.negative_tags),
+            query: self.query,
+            json: self.json,
+            limit: self.limit,
+            page: self.page,
+            id: self.id,
+            credentials: self.credentials,
+            base_url: self.base_url.map(|x| Cow::Owned(x.into_owned())),
+            safety: self.safety,
+            lenient: self.lenient,
+        }
+    }
+    /// Make params which borrow tags of this params
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let owned: R34ParamsOwned = "dark fish".parse().unwrap();
+    /// let borrowed = owned.borrowed().negative_tags(vec!["ai_generated"]);
+    /// assert_eq!(borrowed.tags(), "dark fish -ai_generated");
+    /// ```
+    pub fn borrowed(&self) -> R34Params<'_> {
+        R34Params {
+            p: self.p,
+            s: self.s,
+            q: self.q,
+            positive_tags: self
+                .positive_tags
+                .iter()
+                .map(|x| x.as_ref().into())
+                .collect(),
+            negative_tags: self
+                .negative_tags
+                .iter()
+                .map(|x| x.as_ref().into())
+                .collect(),
+            query: self.query.clone(),
+            json: self.json,
+            limit: self.limit,
+            page: self.page,
+            id: self.id,
+            credentials: self.credentials.clone(),
+            base_url: self.base_url.as_deref().map(Cow::Borrowed),
+            safety: self.safety,
+            lenient: self.lenient,
+        }
+    }
     /// Set positive tags
     ///
     /// ```
@@ -118,8 +210,8 @@ impl<'a> R34Params<'a> {
     ///
     /// ```
     #[inline]
-    pub fn positive_tags(mut self, mut tags: Vec<&'a str>) -> Self {
-        self.positive_tags.append(&mut tags);
+    pub fn positive_tags<T: Into<Cow<'a, str>>>(mut self, tags: Vec<T>) -> Self {
+        self.positive_tags.extend(tags.into_iter().map(Into::into));
         self
     }
     /// Set negative tags
@@ -132,13 +224,116 @@ impl<'a> R34Params<'a> {
     ///
     /// ```
     #[inline]
-    pub fn negative_tags(mut self, mut tags: Vec<&'a str>) -> Self {
-        self.negative_tags.append(&mut tags);
+    pub fn negative_tags<T: Into<Cow<'a, str>>>(mut self, tags: Vec<T>) -> Self {
+        self.negative_tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+    /// Add typed [Query], it will be joined with previous query by `And`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark"])
+    ///     .query(Query::tag("fish").or(Query::tag("shark")));
+    ///
+    /// assert_eq!(result.tags(), "dark ( fish ~ shark )");
+    /// ```
+    #[inline]
+    pub fn query(mut self, query: Query) -> Self {
+        self.query = match self.query {
+            Some(current) => Some(current.and(query)),
+            None => Some(query),
+        };
         self
     }
+    /// Add typed [MetaTag]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .meta(MetaTag::width(Compare::Ge(1920)).unwrap())
+    ///     .meta(MetaTag::rating(Rating::Safe));
+    ///
+    /// assert_eq!(result.tags(), "width:>=1920 rating:safe");
+    /// ```
+    #[inline]
+    pub fn meta(self, meta: MetaTag) -> Self {
+        self.query(meta.into())
+    }
+    /// Find only posts of [MediaKind] by tags `animated` and `video`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark"])
+    ///     .media(MediaKind::Animated);
+    ///
+    /// assert_eq!(result.tags(), "dark animated -video");
+    /// ```
+    #[inline]
+    pub fn media(self, kind: MediaKind) -> Self {
+        self.query(kind.query())
+    }
+    /// Drop posts of [MediaKind] by tags `animated` and `video`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark"])
+    ///     .exclude_media(MediaKind::Video);
+    ///
+    /// assert_eq!(result.tags(), "dark -video");
+    /// ```
+    #[inline]
+    pub fn exclude_media(self, kind: MediaKind) -> Self {
+        self.query(kind.exclude())
+    }
+    /// Set [SafetyPolicy], it will be joined with previous policy,
+    /// so only ratings allowed by both pass
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark"])
+    ///     .safety(SafetyPolicy::max(Rating::Questionable))
+    ///     .safety(SafetyPolicy::allow([Rating::Safe, Rating::Explicit]));
+    ///
+    /// assert_eq!(result.tags(), "dark rating:safe");
+    /// ```
+    #[inline]
+    pub fn safety(mut self, safety: SafetyPolicy) -> Self {
+        self.safety = Some(match self.safety {
+            Some(current) => current.and(safety),
+            None => safety,
+        });
+        self
+    }
+    /// Render all tags in `Rule34` syntax
+    ///
+    /// positive tags, negative tags, [Query], and then metatags of [SafetyPolicy]
+    pub fn tags(&self) -> String {
+        let tags = tag_suppress!(self.positive_tags, self.negative_tags);
+        let query = Query::all(
+            self.query
+                .clone()
+                .into_iter()
+                .chain(self.safety.and_then(|x| x.query())),
+        );
+        match query {
+            Some(query) if tags.is_empty() => query.to_string(),
+            Some(query) => format!("{} {}", tags, query),
+            None => tags,
+        }
+    }
     /// Set limit of links in response
     ///
-    /// max limit <=1000
+    /// max limit <= [R34Params::MAX_LIMIT], checked when url is generated
+    /// or clamped after [R34Params::build_lenient]
     ///
     /// ```
     /// use shuller::prelude::*;
@@ -149,15 +344,7 @@ impl<'a> R34Params<'a> {
     /// ```
     #[inline]
     pub fn limit(mut self, limit: u16) -> Self {
-        if limit > 1000 {
-            eprintln!("Limit is greater then expected:");
-            eprintln!("Expected: any numbers <= 1000, got: {0}", limit);
-            eprintln!("Set limit at 1000");
-
-            self.limit = 1000;
-        } else {
-            self.limit = limit;
-        }
+        self.limit = limit;
         self
     }
     /// start page for find pictures
@@ -189,6 +376,213 @@ impl<'a> R34Params<'a> {
         self
     }
 
+    /// Set [Credentials], they are appended to generated url
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .credentials(Credentials::new("123", "secret"));
+    ///
+    /// ```
+    #[inline]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Set url of api, use it for mirrors, proxies and test servers
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let url = R34Params::init()
+    ///     .base_url("http://localhost:8080/index.php")
+    ///     .try_url_generate()
+    ///     .unwrap();
+    /// assert!(url.as_str().starts_with("http://localhost:8080/index.php?page=dapi"));
+    ///
+    /// assert!(R34Params::init().base_url("not url").try_url_generate().is_err());
+    /// ```
+    #[inline]
+    pub fn base_url(mut self, base_url: impl Into<Cow<'a, str>>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Generate url, never panics
+    ///
+    /// Fails with [Error::Validation] if [R34Params::validate] fails,
+    /// unless params are made by [R34Params::build_lenient]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert!(R34Params::init().limit(5000).try_url_generate().is_err());
+    /// let url = R34Params::init().limit(5000).build_lenient().try_url_generate().unwrap();
+    /// assert!(url.as_str().contains("limit=1000"));
+    /// ```
+    #[inline]
+    pub fn try_url_generate(&self) -> Result<Url> {
+        self.try_url_with(None, None)
+    }
+
+    /// Generate url, own `base_url` and [Credentials] of params take precedence
+    pub(crate) fn try_url_with(
+        &self,
+        base_url: Option<&str>,
+        credentials: Option<&Credentials>,
+    ) -> Result<Url> {
+        if !self.lenient {
+            self.validate()?;
+        }
+        self.url_with(base_url, credentials)
+    }
+
+    /// Generate url without [R34Params::validate], limit is clamped
+    fn url_with(&self, base_url: Option<&str>, credentials: Option<&Credentials>) -> Result<Url> {
+        let url = self
+            .base_url
+            .as_deref()
+            .or(base_url)
+            .unwrap_or(Self::DEFAULT_BASE_URL);
+        let tags = self.tags();
+        let limit = self.limit.min(Self::MAX_LIMIT).to_string();
+        let page = self.page.to_string();
+        let id = self.id.map(|x| x.to_string());
+        let mut params = vec![
+            ("page", self.p),
+            ("s", self.s),
+            ("q", self.q),
+            ("tags", &tags),
+            ("json", toggler!(self.json)),
+            ("limit", &limit),
+            ("pid", &page),
+        ];
+        if let Some(id) = &id {
+            params.push(("id", id));
+        }
+        let mut url = Url::parse_with_params(url, &params)
+            .map_err(|error| Error::InvalidUrl(url.to_string(), error))?;
+        if let Some(credentials) = self.credentials.as_ref().or(credentials) {
+            credentials.apply(&mut url);
+        }
+        Ok(url)
+    }
+
+    /// Params of xml request which only counts posts
+    pub(crate) fn count_params(&self) -> R34Params<'_> {
+        R34Params {
+            json: false,
+            limit: 1,
+            page: 0,
+            ..self.borrowed()
+        }
+    }
+
+    /// Check params, all problems are collected
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark fish", "sea"])
+    ///     .negative_tags(vec!["sea"])
+    ///     .limit(2000)
+    ///     .validate();
+    ///
+    /// assert_eq!(result.unwrap_err().errors().len(), 3);
+    /// ```
+    pub fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        let mut errors = vec![];
+        if self.limit > Self::MAX_LIMIT {
+            errors.push(ValidationError::LimitTooLarge {
+                limit: self.limit,
+                max: Self::MAX_LIMIT,
+            });
+        }
+        for (kind, tags) in [
+            (TagKind::Positive, &self.positive_tags),
+            (TagKind::Negative, &self.negative_tags),
+        ] {
+            for (index, tag) in tags.iter().enumerate() {
+                check_tag(kind, tag, &mut errors);
+                if tags[..index].contains(tag) {
+                    errors.push(ValidationError::Duplicate(kind, tag.to_string()));
+                }
+            }
+        }
+        for (index, tag) in self.positive_tags.iter().enumerate() {
+            if self.negative_tags.contains(tag) && !self.positive_tags[..index].contains(tag) {
+                errors.push(ValidationError::Conflict(tag.to_string()));
+            }
+        }
+        if let Some(query) = &self.query {
+            Self::validate_query(query, &mut errors);
+        }
+        ValidationErrors::check(errors)
+    }
+
+    fn validate_query(query: &Query, errors: &mut Vec<ValidationError>) {
+        match query {
+            Query::Tag(tag) | Query::Wildcard(tag) => check_tag(TagKind::Query, tag, errors),
+            Query::Meta { key, value } => {
+                check_tag(TagKind::Query, &format!("{}:{}", key, value), errors)
+            }
+            Query::Not(inner) => Self::validate_query(inner, errors),
+            Query::And(items) | Query::Or(items) => {
+                items.iter().for_each(|x| Self::validate_query(x, errors))
+            }
+        }
+    }
+
+    /// Finish building, returns params only if [R34Params::validate] passes
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// assert!(R34Params::init().limit(1000).build().is_ok());
+    /// assert!(R34Params::init().limit(1001).build().is_err());
+    /// ```
+    #[inline]
+    pub fn build(self) -> std::result::Result<Self, ValidationErrors> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Finish building without errors
+    ///
+    /// Limit is clamped to [R34Params::MAX_LIMIT],
+    /// empty and duplicated tags are dropped, all other tags are kept as is.
+    /// Url of these params is generated without [R34Params::validate]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let result = R34Params::init()
+    ///     .positive_tags(vec!["dark", "", "dark"])
+    ///     .limit(2000)
+    ///     .build_lenient();
+    ///
+    /// assert_eq!(result.limit, 1000);
+    /// assert_eq!(result.positive_tags, vec!["dark"]);
+    /// ```
+    pub fn build_lenient(mut self) -> Self {
+        self.lenient = true;
+        self.limit = self.limit.min(Self::MAX_LIMIT);
+        for tags in [&mut self.positive_tags, &mut self.negative_tags] {
+            let mut seen = vec![];
+            tags.retain(|x| {
+                if x.is_empty() || seen.contains(x) {
+                    return false;
+                }
+                seen.push(x.clone());
+                true
+            });
+        }
+        self
+    }
+
     /// Set random id for post
     ///
     /// ```
@@ -206,8 +600,95 @@ impl<'a> R34Params<'a> {
     }
 }
 
+/// Requests go through [R34Client::global]
+impl R34Params<'_> {
+    /// Count posts
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// async fn example() {
+    ///     let count = R34Params::init().positive_tags(vec!["dark"]).count().await.unwrap();
+    /// }
+    /// ```
+    #[inline]
+    pub async fn count(&self) -> Result<u64> {
+        R34Client::global().count(self).await
+    }
+
+    /// Download random post, [None] if nothing is found
+    #[inline]
+    pub async fn random(&self) -> Result<Option<Post>> {
+        R34Client::global().random(self).await
+    }
+
+    /// Iterate over pages, starting from `page`
+    #[inline]
+    pub fn pages(&self) -> Pages {
+        R34Client::global().pages(self)
+    }
+}
+
+/// Render tags in `Rule34` syntax, same as [R34Params::tags]
+impl Display for R34Params<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tags())
+    }
+}
+
+/// Parse tags in `Rule34` syntax
+///
+/// Plain tags go to `positive_tags`, `-` prefixed go to `negative_tags`,
+/// all other (metatags, wildcards, groups) go to `query`.
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let params: R34Params = "dark fish -ai_generated score:>10".parse().unwrap();
+///
+/// assert_eq!(params.positive_tags, vec!["dark", "fish"]);
+/// assert_eq!(params.negative_tags, vec!["ai_generated"]);
+/// assert_eq!(params.to_string(), "dark fish -ai_generated score:>10");
+/// ```
+impl FromStr for R34Params<'_> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let items = match s.parse::<Query>()? {
+            Query::And(items) => items,
+            item => vec![item],
+        };
+        let mut params = Self::default();
+        for item in items {
+            match item {
+                Query::Tag(tag) => params.positive_tags.push(tag.into()),
+                Query::Not(inner) => match *inner {
+                    Query::Tag(tag) => params.negative_tags.push(tag.into()),
+                    inner => params = params.query(!inner),
+                },
+                item => params = params.query(item),
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Requests go through [R34Client::global]
 #[async_trait]
-impl JsonDownload<Posts> for R34Params<'_> {}
+impl JsonDownload<Posts> for R34Params<'_> {
+    /// Make url and parse to [Posts]
+    async fn download(&self) -> std::result::Result<Posts, Box<dyn std::error::Error>> {
+        Ok(R34Client::global().download(self).await?)
+    }
+    /// Make url and parse to [Posts],
+    /// url sent by [R34Client::global] is logged by [tracing] with hidden `api_key`
+    async fn download_verbose(&self) -> std::result::Result<Posts, Box<dyn std::error::Error>> {
+        let client = R34Client::global();
+        let url = client.try_url_generate(self)?;
+        tracing::info!(url = %redact_url(&url), "downloading posts");
+        Ok(client.download(self).await?)
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -218,6 +699,9 @@ mod tests {
     use url::Url;
 
     use crate::prelude::R34Params;
+    use crate::rules::rule34::query::Query;
+    use crate::rules::rule34::safety::SafetyPolicy;
+    use crate::rules::rule34::validation::{TagKind, ValidationError};
 
     #[test]
     fn init() {
@@ -230,10 +714,15 @@ mod tests {
                 q: "index",
                 positive_tags: vec![],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 1,
                 page: 1,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
@@ -249,10 +738,15 @@ mod tests {
                 q: "index",
                 positive_tags: vec![],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 1,
                 page: 1,
-                id: Some(2)
+                id: Some(2),
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
@@ -266,12 +760,17 @@ mod tests {
                 p: "dapi",
                 s: "post",
                 q: "index",
-                positive_tags: vec!["test"],
+                positive_tags: vec!["test".into()],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 1,
                 page: 1,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
@@ -285,11 +784,16 @@ mod tests {
                 s: "post",
                 q: "index",
                 positive_tags: vec![],
-                negative_tags: vec!["test"],
+                negative_tags: vec!["test".into()],
+                query: None,
                 json: true,
                 limit: 1,
                 page: 1,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
@@ -304,16 +808,21 @@ mod tests {
                 q: "index",
                 positive_tags: vec![],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 30,
                 page: 1,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
     #[test]
     fn limit_greaten() {
-        let result = R34Params::init().limit(1001);
+        let result = R34Params::init().limit(1001).build_lenient();
         assert_eq!(
             result,
             R34Params {
@@ -322,14 +831,69 @@ mod tests {
                 q: "index",
                 positive_tags: vec![],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 1000,
                 page: 1,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: true
             }
         );
     }
     #[test]
+    fn limit_greaten_strict() {
+        let result = R34Params::init().limit(1001).build();
+        assert_eq!(
+            result.unwrap_err().into_errors(),
+            vec![ValidationError::LimitTooLarge {
+                limit: 1001,
+                max: 1000
+            }]
+        );
+    }
+    #[test]
+    fn limit_greaten_url() {
+        let params = R34Params::init()
+            .positive_tags(vec!["dark fish"])
+            .limit(5000);
+        assert!(params
+            .url_generate()
+            .query_pairs()
+            .any(|(key, value)| key == "limit" && value == "1000"));
+        assert!(params.try_url_generate().is_err());
+    }
+    #[test]
+    fn validate_tags() {
+        let result = R34Params::init()
+            .positive_tags(vec!["", "dark fish", "-sea", "sea", "sea"])
+            .negative_tags(vec!["sea"])
+            .query(Query::tag("a b"))
+            .validate();
+        assert_eq!(
+            result.unwrap_err().into_errors(),
+            vec![
+                ValidationError::EmptyTag(TagKind::Positive),
+                ValidationError::Whitespace(TagKind::Positive, "dark fish".to_string()),
+                ValidationError::LeadingDash(TagKind::Positive, "-sea".to_string()),
+                ValidationError::Duplicate(TagKind::Positive, "sea".to_string()),
+                ValidationError::Conflict("sea".to_string()),
+                ValidationError::Whitespace(TagKind::Query, "a b".to_string()),
+            ]
+        );
+    }
+    #[test]
+    fn validate_ok() {
+        let result = R34Params::init()
+            .positive_tags(vec!["dark"])
+            .negative_tags(vec!["ai_generated"])
+            .query(Query::meta("score", ">=50"))
+            .limit(1000);
+        assert_eq!(result.clone().build(), Ok(result));
+    }
+    #[test]
     fn tag_suppress() {
         use crate::tag_suppress;
         let result = R34Params::init()
@@ -342,6 +906,118 @@ mod tests {
         )
     }
 
+    #[test]
+    fn tag_suppress_without_negative() {
+        use crate::tag_suppress;
+        let result = R34Params::init().positive_tags(vec!["test"]);
+
+        assert_eq!(
+            tag_suppress!(result.positive_tags, result.negative_tags),
+            format!("test")
+        )
+    }
+
+    #[test]
+    fn query() {
+        let result = R34Params::init()
+            .negative_tags(vec!["ai_generated"])
+            .query(Query::tag("fish").or(Query::wildcard("shark*")))
+            .query(Query::meta("score", ">=50"));
+
+        assert_eq!(
+            result.tags(),
+            "-ai_generated ( fish ~ shark* ) score:>=50".to_string()
+        )
+    }
+
+    #[test]
+    fn owned_tags() {
+        let tags = vec!["dark".to_string()];
+        let result = R34Params::init()
+            .positive_tags(tags)
+            .negative_tags(vec!["ai_generated"]);
+
+        assert_eq!(result.tags(), "dark -ai_generated")
+    }
+
+    #[test]
+    fn from_str() {
+        let result: R34Params = "dark ( fish ~ shark ) -ai_generated -rating:explicit score:>10"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            result,
+            R34Params::init()
+                .positive_tags(vec!["dark"])
+                .negative_tags(vec!["ai_generated"])
+                .query(Query::tag("fish").or(Query::tag("shark")))
+                .query(!Query::meta("rating", "explicit"))
+                .query(Query::meta("score", ">10"))
+        );
+        assert!("dark ( fish".parse::<R34Params>().is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "dark fish -ai_generated ( a ~ b* ) score:>10 sort:score:desc";
+        let result: R34Params = text.parse().unwrap();
+        assert_eq!(result.to_string(), text);
+        assert_eq!(result.to_string().parse::<R34Params>().unwrap(), result);
+    }
+
+    #[test]
+    fn base_url() {
+        let result = R34Params::init()
+            .base_url("http://127.0.0.1:8080/index.php")
+            .try_url_generate()
+            .unwrap();
+        assert_eq!(result.host_str(), Some("127.0.0.1"));
+        assert_eq!(result.port(), Some(8080));
+
+        let result = R34Params::init().base_url("http://[::1").try_url_generate();
+        assert!(matches!(result, Err(crate::error::Error::InvalidUrl(_, _))));
+    }
+
+    #[test]
+    fn owned() {
+        let tags = String::from("dark");
+        let borrowed = R34Params::init().positive_tags(vec![tags.as_str()]);
+        let owned = borrowed.clone().into_owned();
+        drop(tags);
+        assert_eq!(owned.borrowed().into_owned(), owned);
+        assert_eq!(owned.tags(), "dark");
+    }
+
+    #[test]
+    fn serde() {
+        let result = R34Params::init()
+            .positive_tags(vec!["dark"])
+            .negative_tags(vec!["ai_generated"])
+            .query(Query::tag("fish").or(Query::tag("shark")))
+            .limit(5)
+            .page(2)
+            .id(3)
+            .safety(SafetyPolicy::safe());
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(serde_json::from_str::<R34Params>(&json).unwrap(), result);
+        assert_eq!(
+            serde_json::from_str::<R34Params>(&json).unwrap().tags(),
+            "dark -ai_generated ( fish ~ shark ) rating:safe"
+        );
+
+        let result: R34Params = serde_json::from_str(r#"{"positive_tags": ["dark"]}"#).unwrap();
+        assert_eq!(result, R34Params::init().positive_tags(vec!["dark"]));
+    }
+
+    #[test]
+    fn hash() {
+        let mut set = std::collections::HashSet::new();
+        set.insert(R34Params::init().positive_tags(vec!["dark"]));
+        set.insert(R34Params::init().positive_tags(vec!["dark".to_string()]));
+        set.insert(R34Params::init().positive_tags(vec!["fish"]));
+        assert_eq!(set.len(), 2);
+    }
+
     #[test]
     fn page() {
         let result = R34Params::init().page(30);
@@ -353,10 +1029,15 @@ mod tests {
                 q: "index",
                 positive_tags: vec![],
                 negative_tags: vec![],
+                query: None,
                 json: true,
                 limit: 1,
                 page: 30,
-                id: None
+                id: None,
+                credentials: None,
+                base_url: None,
+                safety: None,
+                lenient: false
             }
         );
     }
@@ -0,0 +1,551 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::data::Post;
+use super::meta::{MetaError, MetaTag};
+
+/// Typed tag query
+///
+/// Tree of tags which can be rendered into syntax of any backend by [Dialect]
+///
+/// # Example
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let query = Query::tag("dark")
+///     .and(Query::tag("fish").or(Query::wildcard("shark*")))
+///     .and(!Query::tag("ai_generated"))
+///     .and(Query::meta("score", ">=50"));
+///
+/// assert_eq!(
+///     query.to_string(),
+///     "dark ( fish ~ shark* ) -ai_generated score:>=50"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Query {
+    /// Plain tag, like `dark`
+    Tag(String),
+    /// Tag with `*` inside, like `fish*`
+    Wildcard(String),
+    /// Metatag, like `score:>=50`
+    Meta {
+        /// Part before `:`
+        key: String,
+        /// Part after `:`
+        value: String,
+    },
+    /// Exclude all posts which match inner query
+    Not(Box<Query>),
+    /// All of queries must match
+    And(Vec<Query>),
+    /// Any of queries must match
+    Or(Vec<Query>),
+}
+
+impl Query {
+    /// Make plain tag
+    #[inline]
+    pub fn tag(tag: impl Into<String>) -> Self {
+        Self::Tag(tag.into())
+    }
+    /// Make wildcard tag, `*` matches any sequence of chars
+    #[inline]
+    pub fn wildcard(pattern: impl Into<String>) -> Self {
+        Self::Wildcard(pattern.into())
+    }
+    /// Make metatag `key:value`
+    #[inline]
+    pub fn meta(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::Meta {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+    /// Join queries with `And`
+    ///
+    /// Returns [None] if `queries` is empty
+    pub fn all(queries: impl IntoIterator<Item = Query>) -> Option<Self> {
+        queries.into_iter().reduce(Query::and)
+    }
+    /// Join queries with `Or`
+    ///
+    /// Returns [None] if `queries` is empty
+    pub fn any(queries: impl IntoIterator<Item = Query>) -> Option<Self> {
+        queries.into_iter().reduce(Query::or)
+    }
+    /// Both queries must match
+    pub fn and(self, other: Query) -> Self {
+        match (self, other) {
+            (Self::And(mut left), Self::And(right)) => {
+                left.extend(right);
+                Self::And(left)
+            }
+            (Self::And(mut left), right) => {
+                left.push(right);
+                Self::And(left)
+            }
+            (left, Self::And(mut right)) => {
+                right.insert(0, left);
+                Self::And(right)
+            }
+            (left, right) => Self::And(vec![left, right]),
+        }
+    }
+    /// Any of queries must match
+    pub fn or(self, other: Query) -> Self {
+        match (self, other) {
+            (Self::Or(mut left), Self::Or(right)) => {
+                left.extend(right);
+                Self::Or(left)
+            }
+            (Self::Or(mut left), right) => {
+                left.push(right);
+                Self::Or(left)
+            }
+            (left, Self::Or(mut right)) => {
+                right.insert(0, left);
+                Self::Or(right)
+            }
+            (left, right) => Self::Or(vec![left, right]),
+        }
+    }
+
+    /// Render query by [Dialect]
+    #[inline]
+    pub fn render(&self, dialect: &impl Dialect) -> String {
+        dialect.render(self)
+    }
+
+    /// Push all [Query::Not] down to leaves
+    ///
+    /// `-( a ~ b )` becomes `-a -b` and `-(a b)` becomes `( -a ~ -b )`
+    pub fn normalize(self) -> Self {
+        match self {
+            Self::Not(inner) => match *inner {
+                Self::Not(inner) => inner.normalize(),
+                Self::And(items) => Self::Or(
+                    items
+                        .into_iter()
+                        .map(|x| Self::Not(Box::new(x)).normalize())
+                        .collect(),
+                ),
+                Self::Or(items) => Self::And(
+                    items
+                        .into_iter()
+                        .map(|x| Self::Not(Box::new(x)).normalize())
+                        .collect(),
+                ),
+                leaf => Self::Not(Box::new(leaf)),
+            },
+            Self::And(items) => items
+                .into_iter()
+                .map(Query::normalize)
+                .reduce(Query::and)
+                .unwrap_or(Self::And(vec![])),
+            Self::Or(items) => items
+                .into_iter()
+                .map(Query::normalize)
+                .reduce(Query::or)
+                .unwrap_or(Self::Or(vec![])),
+            leaf => leaf,
+        }
+    }
+}
+
+impl Query {
+    /// Check if `post` matches query, the same way as backend does
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    ///
+    /// let post = Post {
+    ///     tags: "dark fish sea".to_string(),
+    ///     score: 60,
+    ///     ..Default::default()
+    /// };
+    /// let query: Query = "dark ( fish* ~ shark ) -ai_generated score:>=50".parse().unwrap();
+    /// assert!(query.matches(&post));
+    /// ```
+    pub fn matches(&self, post: &Post) -> bool {
+        match self {
+            Query::Tag(tag) => post.tags.split_whitespace().any(|x| x == tag),
+            Query::Wildcard(pattern) => post
+                .tags
+                .split_whitespace()
+                .any(|x| wildcard_matches(pattern, x)),
+            Query::Meta { key, value } => match MetaTag::parse(key, value) {
+                Ok(meta) => meta.matches(post),
+                Err(_) => {
+                    let tag = format!("{}:{}", key, value);
+                    post.tags.split_whitespace().any(|x| x == tag)
+                }
+            },
+            Query::Not(inner) => !inner.matches(post),
+            Query::And(items) => items.iter().all(|x| x.matches(post)),
+            Query::Or(items) => items.iter().any(|x| x.matches(post)),
+        }
+    }
+}
+
+/// Check if `text` matches `pattern`, where `*` is any sequence of chars
+pub(crate) fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+impl std::ops::Not for Query {
+    type Output = Query;
+
+    #[inline]
+    fn not(self) -> Self::Output {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl From<&str> for Query {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self::tag(value)
+    }
+}
+
+impl From<String> for Query {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::tag(value)
+    }
+}
+
+/// Render query in [Rule34] syntax
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Rule34.render(self))
+    }
+}
+
+/// Parse query in [Rule34] syntax
+///
+/// Metatags are recognized only for keys known by [MetaTag],
+/// so tags like `re:zero` stay plain tags.
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let query: Query = "dark ( fish ~ shark* ) -ai_generated score:>10".parse().unwrap();
+/// assert_eq!(query.to_string(), "dark ( fish ~ shark* ) -ai_generated score:>10");
+/// ```
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let items = Self::parse_items(&mut tokens)?;
+        Ok(Self::collect(items))
+    }
+}
+
+impl Query {
+    /// Join parsed items, single item isn't wrapped
+    fn collect(mut items: Vec<Query>) -> Query {
+        match items.len() {
+            1 => items.remove(0),
+            _ => Query::And(items),
+        }
+    }
+
+    /// Parse items until end of input
+    fn parse_items<'s>(
+        tokens: &mut impl Iterator<Item = &'s str>,
+    ) -> Result<Vec<Query>, ParseError> {
+        let mut items = vec![];
+        while let Some(token) = tokens.next() {
+            match token {
+                "(" => items.push(Self::parse_group(tokens)?),
+                ")" | "~" => return Err(ParseError::Unexpected(token.to_string())),
+                _ => items.push(Self::parse_term(token)?),
+            }
+        }
+        Ok(items)
+    }
+
+    /// Parse `a ~ b c ~ d )` after opened `(`
+    fn parse_group<'s>(tokens: &mut impl Iterator<Item = &'s str>) -> Result<Query, ParseError> {
+        let mut variants = vec![];
+        let mut current = vec![];
+        loop {
+            match tokens.next() {
+                None => return Err(ParseError::UnclosedGroup),
+                Some("(") => current.push(Self::parse_group(tokens)?),
+                Some(token @ ("~" | ")")) => {
+                    if current.is_empty() {
+                        return Err(ParseError::EmptyGroup);
+                    }
+                    variants.push(Self::collect(std::mem::take(&mut current)));
+                    if token == ")" {
+                        break;
+                    }
+                }
+                Some(token) => current.push(Self::parse_term(token)?),
+            }
+        }
+        Ok(match variants.len() {
+            1 => variants.remove(0),
+            _ => Query::Or(variants),
+        })
+    }
+
+    /// Parse one tag like `-fish*` or `score:>10`
+    fn parse_term(token: &str) -> Result<Query, ParseError> {
+        if let Some(inner) = token.strip_prefix('-') {
+            if inner.is_empty() || inner.starts_with('-') {
+                return Err(ParseError::Unexpected(token.to_string()));
+            }
+            return Ok(!Self::parse_term(inner)?);
+        }
+        if let Some((key, value)) = token.split_once(':') {
+            if MetaTag::is_known(key) {
+                return Ok(MetaTag::parse(key, value)?.into());
+            }
+        }
+        if token.contains('*') {
+            return Ok(Query::wildcard(token));
+        }
+        Ok(Query::tag(token))
+    }
+}
+
+/// Error of parsing query string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// `(` without `)`
+    UnclosedGroup,
+    /// `( ~ )` or `( )`
+    EmptyGroup,
+    /// Token which can't be here, like `)` without `(`
+    Unexpected(String),
+    /// Known metatag with invalid value
+    Meta(MetaError),
+}
+
+impl From<MetaError> for ParseError {
+    #[inline]
+    fn from(value: MetaError) -> Self {
+        Self::Meta(value)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnclosedGroup => write!(f, "group isn't closed by `)`"),
+            ParseError::EmptyGroup => write!(f, "group or variant of group is empty"),
+            ParseError::Unexpected(x) => write!(f, "unexpected token: {:?}", x),
+            ParseError::Meta(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Syntax of tag query for some backend
+///
+/// Implement it if you want to render [Query] for your own rules
+pub trait Dialect {
+    fn render(&self, query: &Query) -> String;
+}
+
+/// Syntax of `Rule34` (and other gelbooru based sites)
+///
+/// * And => `a b`
+/// * Or => `( a ~ b )`
+/// * Not => `-a`
+/// * Wildcard => `a*`
+/// * Meta => `key:value`
+///
+/// **Note:** negation of group is pushed down to tags by [Query::normalize],
+/// `Or` inside `Or` is flattened and `And` inside `Or` is rendered as is,
+/// because `Rule34` has no syntax for it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rule34;
+
+impl Rule34 {
+    fn write(query: &Query, out: &mut Vec<String>) {
+        match query {
+            Query::Tag(tag) | Query::Wildcard(tag) => out.push(tag.to_owned()),
+            Query::Meta { key, value } => out.push(format!("{}:{}", key, value)),
+            Query::Not(inner) => {
+                let mut inner_out = vec![];
+                Self::write(inner, &mut inner_out);
+                out.extend(inner_out.into_iter().map(|x| format!("-{}", x)))
+            }
+            Query::And(items) => items.iter().for_each(|x| Self::write(x, out)),
+            Query::Or(items) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|x| {
+                        let mut item_out = vec![];
+                        Self::write(x, &mut item_out);
+                        item_out.join(" ")
+                    })
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                match items.len() {
+                    0 => {}
+                    1 => out.push(items.into_iter().next().unwrap_or_default()),
+                    _ => out.push(format!("( {} )", items.join(" ~ "))),
+                }
+            }
+        }
+    }
+}
+
+impl Dialect for Rule34 {
+    fn render(&self, query: &Query) -> String {
+        let mut out = vec![];
+        Self::write(&query.clone().normalize(), &mut out);
+        out.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard() {
+        assert!(wildcard_matches("fish*", "fishing"));
+        assert!(wildcard_matches("*fish", "catfish"));
+        assert!(wildcard_matches("a*b*c", "abc"));
+        assert!(wildcard_matches("a*b*c", "a_xb_yc"));
+        assert!(!wildcard_matches("a*b*c", "acb"));
+        assert!(!wildcard_matches("fish", "fishing"));
+        assert!(!wildcard_matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn matches() {
+        let post = Post {
+            id: 5,
+            tags: "dark fish sea re:zero".to_string(),
+            owner: "fisher".to_string(),
+            score: 10,
+            ..Default::default()
+        };
+        let check = |query: &str| query.parse::<Query>().unwrap().matches(&post);
+        assert!(check("dark -shark"));
+        assert!(check("( shark ~ fish )"));
+        assert!(!check("( shark ~ whale )"));
+        assert!(check("score:>5 score:<=10 user:fisher id:5"));
+        assert!(!check("score:>10"));
+        assert!(check("re:zero sea*"));
+        assert!(!check("-dark"));
+    }
+
+    #[test]
+    fn render_tags() {
+        let query = Query::tag("dark").and(Query::tag("fish"));
+        assert_eq!(query.to_string(), "dark fish")
+    }
+
+    #[test]
+    fn render_or() {
+        let query = Query::tag("dark").and(Query::tag("fish").or(Query::tag("shark")));
+        assert_eq!(query.to_string(), "dark ( fish ~ shark )")
+    }
+
+    #[test]
+    fn render_not() {
+        let query = Query::tag("dark").and(!Query::tag("ai_generated"));
+        assert_eq!(query.to_string(), "dark -ai_generated")
+    }
+
+    #[test]
+    fn render_not_group() {
+        let query = !(Query::tag("a").or(Query::tag("b")));
+        assert_eq!(query.to_string(), "-a -b");
+        let query = !(Query::tag("a").and(Query::tag("b")));
+        assert_eq!(query.to_string(), "( -a ~ -b )");
+        let query = !!Query::tag("a");
+        assert_eq!(query.to_string(), "a");
+    }
+
+    #[test]
+    fn render_wildcard_and_meta() {
+        let query = Query::wildcard("fish*").and(Query::meta("score", ">=50"));
+        assert_eq!(query.to_string(), "fish* score:>=50")
+    }
+
+    #[test]
+    fn parse() {
+        let query: Query = "dark ( fish ~ shark* ) -ai_generated -rating:explicit re:zero"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::tag("dark"),
+                Query::Or(vec![Query::tag("fish"), Query::wildcard("shark*")]),
+                !Query::tag("ai_generated"),
+                !Query::meta("rating", "explicit"),
+                Query::tag("re:zero"),
+            ])
+        );
+        assert_eq!("fish".parse(), Ok(Query::tag("fish")));
+        assert_eq!("".parse(), Ok(Query::And(vec![])));
+    }
+
+    #[test]
+    fn parse_error() {
+        assert_eq!("( a ~ b".parse::<Query>(), Err(ParseError::UnclosedGroup));
+        assert_eq!("( a ~ )".parse::<Query>(), Err(ParseError::EmptyGroup));
+        assert_eq!(
+            "a )".parse::<Query>(),
+            Err(ParseError::Unexpected(")".to_string()))
+        );
+        assert_eq!(
+            "a -".parse::<Query>(),
+            Err(ParseError::Unexpected("-".to_string()))
+        );
+        assert!(matches!(
+            "score:>=a".parse::<Query>(),
+            Err(ParseError::Meta(_))
+        ));
+    }
+
+    #[test]
+    fn flatten() {
+        let query = Query::tag("a")
+            .or(Query::tag("b"))
+            .or(Query::tag("c").or(Query::tag("d")));
+        assert_eq!(
+            query,
+            Query::Or(vec![
+                Query::tag("a"),
+                Query::tag("b"),
+                Query::tag("c"),
+                Query::tag("d")
+            ])
+        );
+        assert_eq!(Query::all(vec![]), None);
+        assert_eq!(Query::any(vec!["a".into()]), Some(Query::tag("a")));
+    }
+}
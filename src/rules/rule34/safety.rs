@@ -0,0 +1,218 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::data::{Post, Posts};
+use super::meta::{MetaTag, Rating};
+use super::query::Query;
+
+const RATINGS: [Rating; 3] = [Rating::Safe, Rating::Questionable, Rating::Explicit];
+
+/// Allowed ratings of posts
+///
+/// Adds `rating:` metatags to every query and drops other posts after download,
+/// in case backend ignores metatags. Dropped posts are reported as [Violation]
+///
+/// Policies of [R34Client](super::client::R34Client) and [R34Params](super::params::R34Params)
+/// are joined, so only ratings allowed by both pass
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let params = R34Params::init()
+///     .positive_tags(vec!["dark"])
+///     .safety(SafetyPolicy::safe());
+/// assert_eq!(params.tags(), "dark rating:safe");
+///
+/// let params = R34Params::init().safety(SafetyPolicy::max(Rating::Questionable));
+/// assert_eq!(params.tags(), "-rating:explicit");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SafetyPolicy {
+    safe: bool,
+    questionable: bool,
+    explicit: bool,
+    allow_unknown: bool,
+}
+
+/// Everything is allowed
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        Self::allow(RATINGS).allow_unknown(true)
+    }
+}
+
+impl SafetyPolicy {
+    /// Allow only [Rating::Safe]
+    #[inline]
+    pub fn safe() -> Self {
+        Self::max(Rating::Safe)
+    }
+
+    /// Allow ratings up to `max`, [Rating::Safe] < [Rating::Questionable] < [Rating::Explicit]
+    #[inline]
+    pub fn max(max: Rating) -> Self {
+        Self::allow(RATINGS.into_iter().filter(|x| *x <= max))
+    }
+
+    /// Allow only given `ratings`, posts without rating aren't allowed
+    pub fn allow(ratings: impl IntoIterator<Item = Rating>) -> Self {
+        let mut policy = Self {
+            safe: false,
+            questionable: false,
+            explicit: false,
+            allow_unknown: false,
+        };
+        for rating in ratings {
+            *policy.slot(rating) = true;
+        }
+        policy
+    }
+
+    /// Set if posts without rating are allowed
+    #[inline]
+    pub fn allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    /// Policy which allows only what both policies allow
+    pub fn and(self, other: SafetyPolicy) -> Self {
+        Self {
+            safe: self.safe && other.safe,
+            questionable: self.questionable && other.questionable,
+            explicit: self.explicit && other.explicit,
+            allow_unknown: self.allow_unknown && other.allow_unknown,
+        }
+    }
+
+    fn slot(&mut self, rating: Rating) -> &mut bool {
+        match rating {
+            Rating::Safe => &mut self.safe,
+            Rating::Questionable => &mut self.questionable,
+            Rating::Explicit => &mut self.explicit,
+        }
+    }
+
+    /// Check if `rating` is allowed
+    #[inline]
+    pub fn allows(&self, rating: Rating) -> bool {
+        match rating {
+            Rating::Safe => self.safe,
+            Rating::Questionable => self.questionable,
+            Rating::Explicit => self.explicit,
+        }
+    }
+
+    /// Check if `post` is allowed
+    #[inline]
+    pub fn allows_post(&self, post: &Post) -> bool {
+        match post.rating {
+            Some(rating) => self.allows(rating),
+            None => self.allow_unknown,
+        }
+    }
+
+    /// Metatags of policy, [None] if every rating is allowed
+    ///
+    /// One allowed rating is `rating:x`, otherwise all others are excluded by `-rating:x`
+    pub fn query(&self) -> Option<Query> {
+        let allowed: Vec<Rating> = RATINGS.into_iter().filter(|x| self.allows(*x)).collect();
+        if let [rating] = allowed[..] {
+            return Some(MetaTag::rating(rating).into());
+        }
+        Query::all(
+            RATINGS
+                .into_iter()
+                .filter(|x| !self.allows(*x))
+                .map(|x| !Query::from(MetaTag::rating(x))),
+        )
+    }
+
+    /// Drop not allowed posts, they are returned as [Violation]
+    pub fn filter(&self, posts: Posts) -> (Posts, Vec<Violation>) {
+        let (allowed, dropped): (Vec<Post>, Vec<Post>) =
+            posts.data().into_iter().partition(|x| self.allows_post(x));
+        let violations = dropped
+            .into_iter()
+            .map(|post| Violation {
+                id: post.id,
+                rating: post.rating,
+                policy: *self,
+            })
+            .collect();
+        (allowed.into(), violations)
+    }
+}
+
+/// Post which was dropped by [SafetyPolicy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Violation {
+    /// Id of post
+    pub id: i64,
+    /// Rating of post, [None] if it's unknown
+    pub rating: Option<Rating>,
+    /// Policy which dropped post
+    pub policy: SafetyPolicy,
+}
+
+/// Hook of [R34Client::on_violation](super::client::R34Client::on_violation)
+#[derive(Clone)]
+pub(crate) struct ViolationHook(pub(crate) Arc<dyn Fn(&Violation) + Send + Sync>);
+
+impl Debug for ViolationHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ViolationHook")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(id: i64, rating: Option<Rating>) -> Post {
+        Post {
+            id,
+            rating,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn query() {
+        let render = |x: SafetyPolicy| x.query().map(|x| x.to_string());
+        assert_eq!(render(SafetyPolicy::safe()).as_deref(), Some("rating:safe"));
+        assert_eq!(
+            render(SafetyPolicy::max(Rating::Questionable)).as_deref(),
+            Some("-rating:explicit")
+        );
+        assert_eq!(
+            render(SafetyPolicy::allow([Rating::Explicit, Rating::Safe])).as_deref(),
+            Some("-rating:questionable")
+        );
+        assert_eq!(render(SafetyPolicy::default()), None);
+        assert_eq!(
+            SafetyPolicy::max(Rating::Explicit).and(SafetyPolicy::safe()),
+            SafetyPolicy::safe()
+        );
+    }
+
+    #[test]
+    fn filter() {
+        let posts: Posts = vec![
+            post(1, Some(Rating::Safe)),
+            post(2, Some(Rating::Explicit)),
+            post(3, None),
+        ]
+        .into();
+        let (allowed, violations) = SafetyPolicy::safe().filter(posts.clone());
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(
+            violations.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let (allowed, _) = SafetyPolicy::safe().allow_unknown(true).filter(posts);
+        assert_eq!(allowed.len(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use super::data::Post;
+use crate::error::{Error, Result};
+
+/// Type of tag, like in `type` of `s=tag` api
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagType {
+    /// `0`
+    #[default]
+    General,
+    /// `1`
+    Artist,
+    /// `3`
+    Copyright,
+    /// `4`
+    Character,
+    /// `5`
+    Meta,
+}
+
+impl TagType {
+    /// Type of number in api, unknown numbers are [TagType::General]
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            1 => Self::Artist,
+            3 => Self::Copyright,
+            4 => Self::Character,
+            5 => Self::Meta,
+            _ => Self::General,
+        }
+    }
+
+    /// Number of type in api
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::General => 0,
+            Self::Artist => 1,
+            Self::Copyright => 3,
+            Self::Character => 4,
+            Self::Meta => 5,
+        }
+    }
+
+    /// Name of type, like `artist`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::General => "general",
+            Self::Artist => "artist",
+            Self::Copyright => "copyright",
+            Self::Character => "character",
+            Self::Meta => "meta",
+        }
+    }
+}
+
+impl Display for TagType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Types of tags, get them by [R34Client::tag_types](super::client::R34Client::tag_types)
+///
+/// Api of posts doesn't tell types, so artists of post are found by this map
+///
+/// ```
+/// use shuller::prelude::*;
+///
+/// let types: TagTypes = [("molly", TagType::Artist)].into_iter().collect();
+/// let post = Post { tags: "dark molly fish".to_string(), ..Default::default() };
+/// assert_eq!(types.artists(&post), vec!["molly"]);
+/// assert_eq!(types.get("fish"), None);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TagTypes(HashMap<String, TagType>);
+
+impl TagTypes {
+    /// Type of `tag`, [None] if it's unknown
+    #[inline]
+    pub fn get(&self, tag: &str) -> Option<TagType> {
+        self.0.get(tag).copied()
+    }
+
+    /// Set type of `tag`
+    #[inline]
+    pub fn insert(&mut self, tag: impl Into<String>, tag_type: TagType) {
+        self.0.insert(tag.into(), tag_type);
+    }
+
+    /// Check if type of `tag` is known
+    #[inline]
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.contains_key(tag)
+    }
+
+    /// Tags of `post` with [TagType::Artist], in order of tags
+    pub fn artists<'a>(&self, post: &'a Post) -> Vec<&'a str> {
+        post.tags
+            .split_whitespace()
+            .filter(|x| self.get(x) == Some(TagType::Artist))
+            .collect()
+    }
+
+    /// Number of known tags
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if no types are known
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parse answer of `s=tag` api, like `<tags><tag type="1" name="molly"/></tags>`
+    pub fn from_xml(body: &[u8]) -> Result<Self> {
+        let body = String::from_utf8_lossy(body);
+        if !body.contains("<tags") {
+            return Err(Error::Decode(serde::de::Error::custom(
+                "no tags in xml of tag types",
+            )));
+        }
+        let mut types = Self::default();
+        for element in body.split("<tag ").skip(1) {
+            let element = element.split('>').next().unwrap_or_default();
+            let Some(name) = attribute(element, "name") else {
+                continue;
+            };
+            let code = attribute(element, "type").and_then(|x| x.parse().ok());
+            types.insert(name, TagType::from_code(code.unwrap_or(0)));
+        }
+        Ok(types)
+    }
+}
+
+impl FromIterator<(String, TagType)> for TagTypes {
+    fn from_iter<T: IntoIterator<Item = (String, TagType)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'a> FromIterator<(&'a str, TagType)> for TagTypes {
+    fn from_iter<T: IntoIterator<Item = (&'a str, TagType)>>(iter: T) -> Self {
+        iter.into_iter().map(|(x, y)| (x.to_string(), y)).collect()
+    }
+}
+
+impl Extend<(String, TagType)> for TagTypes {
+    fn extend<T: IntoIterator<Item = (String, TagType)>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for TagTypes {
+    type Item = (String, TagType);
+    type IntoIter = std::collections::hash_map::IntoIter<String, TagType>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Unescaped value of `key="value"` in xml element
+fn attribute(element: &str, key: &str) -> Option<String> {
+    let start = element
+        .match_indices(&format!("{}=\"", key))
+        .find(|(index, _)| *index == 0 || element[..*index].ends_with(char::is_whitespace))
+        .map(|(index, x)| index + x.len())?;
+    let end = element[start..].find('"')? + start;
+    Some(
+        element[start..end]
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?><tags type="array">
+            <tag type="1" count="12" name="molly" ambiguous="false" id="1"/>
+            <tag type="0" count="99" name="fish&amp;chips" ambiguous="false" id="2"/>
+            <tag count="3" name="sea" id="3" type="4"/>
+            <tag type="x" name="odd" id="4"/>
+        </tags>"#;
+        let types = TagTypes::from_xml(xml.as_bytes()).unwrap();
+        assert_eq!(types.len(), 4);
+        assert_eq!(types.get("molly"), Some(TagType::Artist));
+        assert_eq!(types.get("fish&chips"), Some(TagType::General));
+        assert_eq!(types.get("sea"), Some(TagType::Character));
+        assert_eq!(types.get("odd"), Some(TagType::General));
+        assert!(TagTypes::from_xml(b"[]").is_err());
+        assert!(TagTypes::from_xml(b"<tags></tags>").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Names of months in dates like `Sat Jul 06 12:34:56 +0000 2024`
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Time in UTC with precision of second, like `change` and `created_at` of post
+///
+/// Parsed from unix time, `2024-07-06T12:34:56Z` or `Sat Jul 06 12:34:56 +0000 2024`,
+/// serialized as unix time
+///
+/// ```
+/// use shuller::rules::rule34::time::DateTime;
+///
+/// let time: DateTime = "Sat Jul 06 12:34:56 +0200 2024".parse().unwrap();
+/// assert_eq!(time.to_string(), "2024-07-06T10:34:56Z");
+/// assert_eq!(time, DateTime::from_unix(1720262096));
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DateTime(i64);
+
+impl DateTime {
+    /// Make time from seconds since `1970-01-01T00:00:00Z`
+    #[inline]
+    pub fn from_unix(seconds: i64) -> Self {
+        Self(seconds)
+    }
+
+    /// Seconds since `1970-01-01T00:00:00Z`
+    #[inline]
+    pub fn unix(&self) -> i64 {
+        self.0
+    }
+
+    /// Current time
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    /// Make time from date and time of day in UTC, [None] if they aren't valid
+    pub fn from_parts(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Option<Self> {
+        let valid = (1..=12).contains(&month)
+            && (1..=days_in_month(year, month)).contains(&day)
+            && hour < 24
+            && minute < 60
+            && second <= 60;
+        if !valid {
+            return None;
+        }
+        days_from_civil(year, month, day)?
+            .checked_mul(86400)?
+            .checked_add((hour * 3600 + minute * 60 + second) as i64)
+            .map(Self)
+    }
+}
+
+impl From<SystemTime> for DateTime {
+    fn from(value: SystemTime) -> Self {
+        match value.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(x) => Self(i64::try_from(x.as_secs()).unwrap_or(i64::MAX)),
+            Err(x) => Self(-i64::try_from(x.duration().as_secs()).unwrap_or(i64::MAX)),
+        }
+    }
+}
+
+/// Fails if time can't be represented by [SystemTime] of platform
+impl TryFrom<DateTime> for SystemTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: DateTime) -> Result<Self, Self::Error> {
+        let seconds = Duration::from_secs(value.0.unsigned_abs());
+        match value.0 >= 0 {
+            true => SystemTime::UNIX_EPOCH.checked_add(seconds),
+            false => SystemTime::UNIX_EPOCH.checked_sub(seconds),
+        }
+        .ok_or_else(|| DateTimeError(value.0.to_string()))
+    }
+}
+
+/// Render time like `2024-07-06T12:34:56Z`
+impl Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_from_days(self.0.div_euclid(86400));
+        let time = self.0.rem_euclid(86400);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        )
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = DateTimeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let error = || DateTimeError(s.to_string());
+        if let Ok(seconds) = s.parse() {
+            return Ok(Self(seconds));
+        }
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        match parts.as_slice() {
+            // Sat Jul 06 12:34:56 +0000 2024
+            [_, month, day, time, offset, year] => {
+                let month = MONTHS.iter().position(|x| x == month).ok_or_else(error)? as u32 + 1;
+                let offset = parse_offset(offset).ok_or_else(error)?;
+                let time = parse_date_time(year, month, day, time).ok_or_else(error)?;
+                time.0.checked_sub(offset).map(Self).ok_or_else(error)
+            }
+            // 2024-07-06T12:34:56Z, 2024-07-06 12:34:56
+            _ => {
+                let (date, time) = s.split_once(['T', ' ']).ok_or_else(error)?;
+                let (time, offset) = match time.find(['Z', '+', '-']) {
+                    Some(index) => (
+                        &time[..index],
+                        parse_offset(&time[index..]).ok_or_else(error)?,
+                    ),
+                    None => (time, 0),
+                };
+                // drop fraction of second
+                let time = time.split('.').next().unwrap_or_default();
+                let [year, month, day] = date.split('-').collect::<Vec<_>>()[..] else {
+                    return Err(error());
+                };
+                let month = month.parse().map_err(|_| error())?;
+                let time = parse_date_time(year, month, day, time).ok_or_else(error)?;
+                time.0.checked_sub(offset).map(Self).ok_or_else(error)
+            }
+        }
+    }
+}
+
+impl Serialize for DateTime {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Number(x) => x
+                .as_i64()
+                .map(Self)
+                .ok_or_else(|| serde::de::Error::custom(DateTimeError(x.to_string()))),
+            serde_json::Value::String(x) => x.parse().map_err(serde::de::Error::custom),
+            x => Err(serde::de::Error::custom(DateTimeError(x.to_string()))),
+        }
+    }
+}
+
+/// Error of parsing [DateTime]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeError(String);
+
+impl Display for DateTimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid date time: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for DateTimeError {}
+
+/// Parse `year`, `day` and `12:34:56`
+fn parse_date_time(year: &str, month: u32, day: &str, time: &str) -> Option<DateTime> {
+    let time: Vec<u32> = time
+        .split(':')
+        .map(|x| x.parse().ok())
+        .collect::<Option<_>>()?;
+    let [hour, minute, second] = time[..] else {
+        return None;
+    };
+    DateTime::from_parts(
+        year.parse().ok()?,
+        month,
+        day.parse().ok()?,
+        hour,
+        minute,
+        second,
+    )
+}
+
+/// Parse `Z`, `+0200` or `-02:00` into seconds
+fn parse_offset(offset: &str) -> Option<i64> {
+    if offset == "Z" || offset == "GMT" {
+        return Some(0);
+    }
+    let (sign, digits) = match offset.split_at_checked(1)? {
+        ("+", x) => (1, x),
+        ("-", x) => (-1, x),
+        _ => return None,
+    };
+    let digits = digits.replace(':', "");
+    if digits.len() != 4 || !digits.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since `1970-01-01` of date in civil calendar, [None] on overflow
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era.checked_mul(146097)?.checked_add(doe - 719468)
+}
+
+/// Date in civil calendar of days since `1970-01-01`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let time = DateTime::from_unix(1720269296);
+        assert_eq!(time.to_string(), "2024-07-06T12:34:56Z");
+        for text in [
+            "1720269296",
+            "2024-07-06T12:34:56Z",
+            "2024-07-06T12:34:56.123+00:00",
+            "2024-07-06 14:34:56+02:00",
+            "2024-07-06 12:34:56",
+            "Sat Jul 06 12:34:56 +0000 2024",
+            "Sat Jul 06 07:34:56 -0500 2024",
+        ] {
+            assert_eq!(text.parse::<DateTime>(), Ok(time), "{}", text);
+        }
+        assert!("2024-02-30T00:00:00Z".parse::<DateTime>().is_err());
+        assert!("yesterday".parse::<DateTime>().is_err());
+        assert_eq!(
+            "1969-12-31T23:59:59Z".parse::<DateTime>(),
+            Ok(DateTime::from_unix(-1))
+        );
+    }
+
+    #[test]
+    fn serde() {
+        let time: DateTime = serde_json::from_str("\"2024-07-06T12:34:56Z\"").unwrap();
+        assert_eq!(serde_json::to_string(&time).unwrap(), "1720269296");
+        let time: DateTime = serde_json::from_str("1720269296").unwrap();
+        assert_eq!(time.unix(), 1720269296);
+        assert!(serde_json::from_str::<DateTime>("true").is_err());
+    }
+
+    #[test]
+    fn civil() {
+        for days in [-1000000, -1, 0, 1, 19910, 1000000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), Some(days));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(DateTime::from_parts(i64::MAX, 12, 31, 0, 0, 0), None);
+        assert_eq!(DateTime::from_parts(i64::MIN, 1, 1, 0, 0, 0), None);
+        assert!("Sat Jan 01 00:00:00 +0000 -9223372036854775808"
+            .parse::<DateTime>()
+            .is_err());
+        assert!("99999999999999-01-01T00:00:00Z"
+            .parse::<DateTime>()
+            .is_err());
+        assert_eq!(
+            SystemTime::try_from(DateTime::from_unix(-1)),
+            Ok(SystemTime::UNIX_EPOCH - Duration::from_secs(1))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Display;
+
+/// Where invalid tag was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagKind {
+    /// `positive_tags`
+    Positive,
+    /// `negative_tags`
+    Negative,
+    /// tag inside of `query`
+    Query,
+}
+
+impl Display for TagKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagKind::Positive => write!(f, "positive"),
+            TagKind::Negative => write!(f, "negative"),
+            TagKind::Query => write!(f, "query"),
+        }
+    }
+}
+
+/// One problem of [R34Params](super::params::R34Params)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ValidationError {
+    /// Limit is greater than [R34Params::MAX_LIMIT](super::params::R34Params::MAX_LIMIT)
+    LimitTooLarge {
+        /// Given limit
+        limit: u16,
+        /// Max limit of api
+        max: u16,
+    },
+    /// Tag is empty string
+    EmptyTag(TagKind),
+    /// Tag contains whitespace, so api sees it as several tags
+    Whitespace(TagKind, String),
+    /// Tag starts with `-`, so api sees it as negative tag
+    LeadingDash(TagKind, String),
+    /// Same tag is given twice
+    Duplicate(TagKind, String),
+    /// Tag is positive and negative at the same time
+    Conflict(String),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::LimitTooLarge { limit, max } => {
+                write!(f, "limit must be <= {}, got: {}", max, limit)
+            }
+            ValidationError::EmptyTag(kind) => write!(f, "{} tag is empty", kind),
+            ValidationError::Whitespace(kind, tag) => {
+                write!(f, "{} tag contains whitespace: {:?}", kind, tag)
+            }
+            ValidationError::LeadingDash(kind, tag) => {
+                write!(f, "{} tag starts with `-`: {:?}", kind, tag)
+            }
+            ValidationError::Duplicate(kind, tag) => {
+                write!(f, "{} tag is duplicated: {:?}", kind, tag)
+            }
+            ValidationError::Conflict(tag) => {
+                write!(f, "tag is positive and negative: {:?}", tag)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// All problems of [R34Params](super::params::R34Params), never empty
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationErrors(Vec<ValidationError>);
+
+impl ValidationErrors {
+    /// Make [Err] if there is any error
+    pub(crate) fn check(errors: Vec<ValidationError>) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self(errors))
+        }
+    }
+
+    /// List of errors
+    #[inline]
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.0
+    }
+
+    /// Take [Vec] of errors
+    #[inline]
+    pub fn into_errors(self) -> Vec<ValidationError> {
+        self.0
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self.0.iter().map(|x| x.to_string()).collect();
+        write!(f, "invalid params: {}", errors.join(", "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl IntoIterator for ValidationErrors {
+    type Item = ValidationError;
+    type IntoIter = std::vec::IntoIter<ValidationError>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// Check one tag
+pub(crate) fn check_tag(kind: TagKind, tag: &str, errors: &mut Vec<ValidationError>) {
+    if tag.is_empty() {
+        errors.push(ValidationError::EmptyTag(kind));
+    } else if tag.chars().any(char::is_whitespace) {
+        errors.push(ValidationError::Whitespace(kind, tag.to_string()));
+    } else if tag.starts_with('-') {
+        errors.push(ValidationError::LeadingDash(kind, tag.to_string()));
+    }
+}
@@ -0,0 +1,194 @@
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use uller::Url;
+
+use crate::testing::FakeBooru;
+use crate::transport::{header, Response, StatusCode};
+
+/// Max size of request head
+const MAX_HEAD: usize = 16 * 1024;
+
+/// Local `Rule34` compatible api over index of [FakeBooru]
+///
+/// Serves `GET /index.php?page=dapi&s=post&q=index` with json and xml answers,
+/// so any [R34Params](crate::prelude::R34Params) works with it
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::serve::Server;
+/// use shuller::testing::FakeBooru;
+///
+/// async fn example() {
+///     let server = Server::bind("127.0.0.1:8080", FakeBooru::init().generate(1000))
+///         .await
+///         .unwrap();
+///     let client = R34Client::init().base_url(server.base_url());
+///     tokio::spawn(server.run());
+///     let posts = client.download(&R34Params::init().positive_tags(vec!["dark"])).await;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    booru: FakeBooru,
+}
+
+impl Server {
+    /// Listen on `addr`, use port `0` to get any free port
+    pub async fn bind(addr: impl ToSocketAddrs, booru: FakeBooru) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            booru,
+        })
+    }
+
+    #[inline]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Url of api, use it as `base_url` of [R34Client](crate::prelude::R34Client)
+    pub fn base_url(&self) -> String {
+        match self.local_addr() {
+            Ok(addr) => format!("http://{}/index.php", addr),
+            Err(_) => "http://localhost/index.php".to_string(),
+        }
+    }
+
+    /// Serve requests until error of listener, every connection is served by own task
+    pub async fn run(self) -> io::Result<()> {
+        let base_url = self.base_url();
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let booru = self.booru.clone();
+            let base_url = base_url.clone();
+            tokio::spawn(async move {
+                if let Err(error) = serve(stream, &booru, &base_url).await {
+                    tracing::debug!(error = %error, "connection failed");
+                }
+            });
+        }
+    }
+}
+
+/// Serve one request, connection is closed after it
+async fn serve(mut stream: TcpStream, booru: &FakeBooru, base_url: &str) -> io::Result<()> {
+    let mut head = Vec::with_capacity(1024);
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|x| x == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..read]);
+        if head.len() > MAX_HEAD {
+            return write(
+                &mut stream,
+                Response::new(StatusCode::PAYLOAD_TOO_LARGE, ""),
+            )
+            .await;
+        }
+    }
+    let line = String::from_utf8_lossy(&head);
+    let line = line.lines().next().unwrap_or_default();
+    let response = match line.split(' ').collect::<Vec<_>>().as_slice() {
+        ["GET", target, version] if version.starts_with("HTTP/1.") => {
+            route(booru, base_url, target)
+        }
+        [_, _, _] => Response::new(StatusCode::METHOD_NOT_ALLOWED, ""),
+        _ => Response::new(StatusCode::BAD_REQUEST, ""),
+    };
+    write(&mut stream, response).await
+}
+
+/// Answer of `target` like `/index.php?page=dapi&s=post&q=index&tags=dark`
+fn route(booru: &FakeBooru, base_url: &str, target: &str) -> Response {
+    let Ok(url) = Url::parse(base_url).and_then(|x| x.join(target)) else {
+        return Response::new(StatusCode::BAD_REQUEST, "");
+    };
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    };
+    let is_api = url.path() == "/index.php"
+        && query("page").as_deref() == Some("dapi")
+        && query("s").as_deref() == Some("post")
+        && query("q").as_deref() == Some("index");
+    match is_api {
+        true => booru.respond(&url),
+        false => Response::new(StatusCode::NOT_FOUND, ""),
+    }
+}
+
+async fn write(stream: &mut TcpStream, response: Response) -> io::Result<()> {
+    let status = response.status;
+    let content_type = response
+        .header_str(header::CONTENT_TYPE.as_str())
+        .unwrap_or("text/plain")
+        .to_string();
+    let body = response.body.bytes().await.map_err(io::Error::other)?;
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn serve() {
+        let booru = FakeBooru::init()
+            .json(include_bytes!("../tests/fixtures/posts.json"))
+            .unwrap()
+            .generate(30);
+        let server = Server::bind("127.0.0.1:0", booru).await.unwrap();
+        let client = R34Client::init()
+            .base_url(server.base_url())
+            .retry(RetryPolicy::none());
+        let base_url = server.base_url();
+        tokio::spawn(server.run());
+
+        let params = R34Params::init().limit(10).page(0);
+        assert_eq!(client.download(&params).await.unwrap().len(), 10);
+        assert_eq!(client.count(&params).await.unwrap(), 33);
+
+        let posts = client
+            .download(
+                &params
+                    .clone()
+                    .positive_tags(vec!["fish", "sea"])
+                    .negative_tags(vec!["video"]),
+            )
+            .await
+            .unwrap();
+        assert!(posts.data_ref().iter().any(|x| x.id == 10542274));
+        assert!(posts.data_ref().iter().all(|x| !x.tags.contains("video")));
+
+        let posts = client
+            .download(&R34Params::init().id(10542300))
+            .await
+            .unwrap();
+        assert_eq!(posts.data_ref()[0].id, 10542300);
+
+        let not_api = base_url.replace("index.php", "other.php");
+        let client = R34Client::init()
+            .base_url(not_api)
+            .retry(RetryPolicy::none());
+        assert!(matches!(
+            client.download(&params).await,
+            Err(crate::error::Error::Status(StatusCode::NOT_FOUND))
+        ));
+    }
+}
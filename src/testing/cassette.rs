@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::rules::rule34::credentials::redact_credentials;
+use crate::transport::{Request, ReqwestTransport, Response, StatusCode, Transport};
+
+/// Body of [Interaction], text is stored as is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&Bytes> for StoredBody {
+    fn from(value: &Bytes) -> Self {
+        match std::str::from_utf8(value) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Bytes(value.to_vec()),
+        }
+    }
+}
+
+impl From<&StoredBody> for Bytes {
+    fn from(value: &StoredBody) -> Self {
+        match value {
+            StoredBody::Text(x) => Bytes::copy_from_slice(x.as_bytes()),
+            StoredBody::Bytes(x) => Bytes::copy_from_slice(x),
+        }
+    }
+}
+
+/// One recorded request and its response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Interaction {
+    /// Url without `api_key` and `user_id`
+    url: String,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: StoredBody,
+    /// Interaction was replayed
+    #[serde(skip)]
+    used: bool,
+}
+
+/// File of [Cassette]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone)]
+enum Mode {
+    Record(Arc<dyn Transport>),
+    Replay { strict: bool },
+}
+
+/// [Transport] which records real responses into json file and replays them byte for byte
+///
+/// `api_key` and `user_id` are never stored, requests are matched without them
+///
+/// Same requests are replayed in order of recording, the last one is repeated
+///
+/// ```no_run
+/// use shuller::prelude::*;
+/// use shuller::testing::cassette::Cassette;
+///
+/// async fn example() {
+///     let params = R34Params::init().positive_tags(vec!["dark"]).limit(3);
+///
+///     // once, with network
+///     let client = R34Client::init().transport(Cassette::record("tests/cassettes/dark.json"));
+///     client.download(&params).await.unwrap();
+///
+///     // in CI
+///     let cassette = Cassette::replay("tests/cassettes/dark.json").unwrap();
+///     let client = R34Client::init().transport(cassette);
+///     client.download(&params).await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    tape: Arc<Mutex<Tape>>,
+    /// Held while file is written, so newer tape is never overwritten by older one
+    writer: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Cassette {
+    /// Record responses of default [ReqwestTransport] into `path`
+    #[inline]
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self::record_with(path, ReqwestTransport::default())
+    }
+
+    /// Record responses of `transport` into `path`, file is rewritten after every request
+    pub fn record_with(path: impl Into<PathBuf>, transport: impl Transport + 'static) -> Self {
+        Self {
+            path: path.into(),
+            mode: Mode::Record(Arc::new(transport)),
+            tape: Default::default(),
+            writer: Default::default(),
+        }
+    }
+
+    /// Replay responses from `path` in strict mode
+    pub fn replay(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let tape = serde_json::from_slice(&std::fs::read(&path)?)?;
+        Ok(Self {
+            path,
+            mode: Mode::Replay { strict: true },
+            tape: Arc::new(Mutex::new(tape)),
+            writer: Default::default(),
+        })
+    }
+
+    /// Set strict mode of replay
+    ///
+    /// Unknown requests are [Error::NotRecorded] in strict mode and `404 Not Found` otherwise
+    #[inline]
+    pub fn strict(mut self, strict: bool) -> Self {
+        if let Mode::Replay { strict: x } = &mut self.mode {
+            *x = strict;
+        }
+        self
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of recorded requests
+    pub fn len(&self) -> usize {
+        self.lock().interactions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Tape> {
+        self.tape.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Send request by inner transport and store its response
+    async fn record_send(&self, transport: &dyn Transport, request: Request) -> Result<Response> {
+        let url = redact_credentials(&request.url).to_string();
+        let response = transport.send(request).await?;
+        let headers = response
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let status = response.status;
+        let body = response.body.bytes().await?;
+        self.lock().interactions.push(Interaction {
+            url,
+            status: status.as_u16(),
+            headers,
+            body: StoredBody::from(&body),
+            used: false,
+        });
+        // tape is taken after writer lock, so the last written file has all interactions
+        let _writer = self.writer.lock().await;
+        let json = serde_json::to_vec_pretty(&*self.lock())?;
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.path, json).await?;
+        Ok(Response {
+            headers: response.headers,
+            ..Response::new(status, body)
+        })
+    }
+
+    /// Find stored response of request
+    fn replay_send(&self, request: &Request, strict: bool) -> Result<Response> {
+        let url = redact_credentials(&request.url).to_string();
+        let mut tape = self.lock();
+        let found = tape
+            .interactions
+            .iter()
+            .position(|x| !x.used && x.url == url)
+            .or_else(|| tape.interactions.iter().rposition(|x| x.url == url));
+        let Some(index) = found else {
+            return match strict {
+                true => Err(Error::NotRecorded {
+                    cassette: self.path.clone(),
+                    url,
+                }),
+                false => Ok(Response::new(StatusCode::NOT_FOUND, "")),
+            };
+        };
+        let interaction = &mut tape.interactions[index];
+        interaction.used = true;
+        let status = StatusCode::from_u16(interaction.status).map_err(Error::transport)?;
+        let mut response = Response::new(status, Bytes::from(&interaction.body));
+        for (name, value) in &interaction.headers {
+            response = response.header(name, value);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Transport for Cassette {
+    async fn send(&self, request: Request) -> Result<Response> {
+        match &self.mode {
+            Mode::Record(transport) => self.record_send(transport.as_ref(), request).await,
+            Mode::Replay { strict } => self.replay_send(&request, *strict),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::testing::FakeBooru;
+
+    #[tokio::test]
+    async fn record_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "shuller-cassette-test-{}/tape.json",
+            std::process::id()
+        ));
+        let params = R34Params::init()
+            .positive_tags(vec!["fish"])
+            .limit(5)
+            .credentials(Credentials::new("123", "secret"));
+        let booru = FakeBooru::init().generate(30);
+        let recorder = Cassette::record_with(&path, booru.clone());
+        let client = R34Client::init().transport(recorder.clone());
+        let recorded = client.download(&params).await.unwrap();
+        assert_eq!(recorder.len(), 1);
+
+        let file = std::fs::read_to_string(&path).unwrap();
+        assert!(!file.contains("secret"));
+        assert!(!file.contains("123"));
+
+        let cassette = Cassette::replay(&path).unwrap();
+        let client = R34Client::init()
+            .transport(cassette.clone())
+            .retry(RetryPolicy::none());
+        // other credentials match too
+        let params = params.credentials(Credentials::new("456", "other"));
+        assert_eq!(client.download(&params).await.unwrap(), recorded);
+        assert_eq!(client.download(&params).await.unwrap(), recorded);
+        assert_eq!(booru.requests().len(), 1);
+
+        let unknown = R34Params::init().positive_tags(vec!["whale"]);
+        let error = client.download(&unknown).await.unwrap_err();
+        assert!(matches!(error, Error::NotRecorded { .. }));
+        assert!(!error.is_retryable());
+        let client = R34Client::init()
+            .transport(cassette.strict(false))
+            .retry(RetryPolicy::none());
+        assert!(matches!(
+            client.download(&unknown).await,
+            Err(Error::Status(StatusCode::NOT_FOUND))
+        ));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn body() {
+        let text = Bytes::from("[]");
+        assert_eq!(StoredBody::from(&text), StoredBody::Text("[]".to_string()));
+        let binary = Bytes::from(vec![0xff, 0x00]);
+        let stored = StoredBody::from(&binary);
+        assert_eq!(stored, StoredBody::Bytes(vec![0xff, 0x00]));
+        let json = serde_json::to_string(&stored).unwrap();
+        let stored: StoredBody = serde_json::from_str(&json).unwrap();
+        assert_eq!(Bytes::from(&stored), binary);
+    }
+}
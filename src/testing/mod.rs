@@ -0,0 +1,459 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use uller::Url;
+
+use crate::error::Result;
+use crate::retry::RetryPolicy;
+use crate::rules::rule34::client::R34Client;
+use crate::rules::rule34::data::{Post, Posts};
+use crate::rules::rule34::meta::{MetaTag, Rating, SortKey, SortOrder};
+use crate::rules::rule34::params::{R34Params, R34ParamsOwned};
+use crate::rules::rule34::query::Query;
+use crate::rules::rule34::tag_type::{TagType, TagTypes};
+use crate::rules::rule34::time::DateTime;
+use crate::transport::{Request, Response, StatusCode, Transport};
+
+/// Record and replay of real responses, see [cassette::Cassette]
+pub mod cassette;
+
+/// Tags of [FakeBooru::fake_post]
+const TAGS: [&str; 10] = [
+    "dark",
+    "fish",
+    "sea",
+    "shark",
+    "underwater",
+    "animated",
+    "ai_generated",
+    "video",
+    "sound",
+    "solo",
+];
+
+/// Fake `Rule34` api in memory, [Transport] which never goes to network
+///
+/// Honours tags, negative tags, metatags, `limit`, `pid` and `id`,
+/// answers xml with `count` when `json` is off, like real api
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::testing::FakeBooru;
+///
+/// async fn example() {
+///     let booru = FakeBooru::init().generate(100);
+///     let client = booru.client();
+///     let posts = client
+///         .download(&R34Params::init().positive_tags(vec!["fish"]).limit(5))
+///         .await
+///         .unwrap();
+///     assert_eq!(posts.len(), 5);
+///     assert_eq!(booru.requests().len(), 1);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FakeBooru {
+    posts: Arc<Vec<Post>>,
+    requests: Arc<Mutex<Vec<Url>>>,
+    any_id: bool,
+    tag_types: Arc<TagTypes>,
+}
+
+impl FakeBooru {
+    /// Init booru without posts
+    #[inline]
+    pub fn init() -> Self {
+        Self::default()
+    }
+
+    /// Add `posts`
+    pub fn posts(mut self, posts: impl Into<Posts>) -> Self {
+        Arc::make_mut(&mut self.posts).extend(posts.into().data());
+        self
+    }
+
+    /// Add posts from json fixture, the same as answer of api
+    ///
+    /// ```
+    /// use shuller::testing::FakeBooru;
+    ///
+    /// let booru = FakeBooru::init().json(b"[]").unwrap();
+    /// ```
+    pub fn json(self, json: &[u8]) -> Result<Self> {
+        Ok(self.posts(Posts::from_json(json)?))
+    }
+
+    /// Add `count` posts by [FakeBooru::fake_post]
+    #[inline]
+    pub fn generate(self, count: u64) -> Self {
+        self.generate_with(count, Self::fake_post)
+    }
+
+    /// Add `count` posts by `generator`, it gets ids after the last post
+    pub fn generate_with(mut self, count: u64, generator: impl FnMut(u64) -> Post) -> Self {
+        let start = self.posts.iter().map(|x| x.id).max().unwrap_or(0).max(0) as u64 + 1;
+        let posts = (start..start + count).map(generator);
+        Arc::make_mut(&mut self.posts).extend(posts);
+        self
+    }
+
+    /// Answer `id` of not added post by [FakeBooru::fake_post], like random id of `R34!(R; D)`
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    /// use shuller::testing::FakeBooru;
+    ///
+    /// let booru = FakeBooru::init().any_id();
+    /// let (count, posts) = booru.search(&R34Params::init().id(123456));
+    /// assert_eq!((count, posts[0].id), (1, 123456));
+    /// ```
+    #[inline]
+    pub fn any_id(mut self) -> Self {
+        self.any_id = true;
+        self
+    }
+
+    /// Set type of `tag` for `s=tag` api, other tags are [TagType::General]
+    ///
+    /// ```
+    /// use shuller::prelude::*;
+    /// use shuller::testing::FakeBooru;
+    ///
+    /// async fn example() {
+    ///     let booru = FakeBooru::init().tag_type("molly", TagType::Artist);
+    ///     let types = booru.client().tag_types(["molly", "fish"]).await.unwrap();
+    ///     assert_eq!(types.get("molly"), Some(TagType::Artist));
+    ///     assert_eq!(types.get("fish"), Some(TagType::General));
+    /// }
+    /// ```
+    pub fn tag_type(mut self, tag: impl Into<String>, tag_type: TagType) -> Self {
+        Arc::make_mut(&mut self.tag_types).insert(tag, tag_type);
+        self
+    }
+
+    /// Post with `id`, its tags and numbers depend only on `id`
+    pub fn fake_post(id: u64) -> Post {
+        let mut seed = id
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let tags: Vec<&str> = TAGS
+            .iter()
+            .filter(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 33).is_multiple_of(3)
+            })
+            .copied()
+            .collect();
+        let url = |kind: &str| format!("https://fake.booru/{}/{}.png", kind, id);
+        Post {
+            preview_url: url("thumbnails"),
+            sample_url: url("samples"),
+            file_url: url("images"),
+            directory: (id / 1000) as i64,
+            hash: format!("{:032x}", id),
+            width: 400 + (id % 7 * 100) as i64,
+            height: 300 + (id % 5 * 100) as i64,
+            id: id as i64,
+            image: format!("{}.png", id),
+            // one post per minute since 2024-01-01
+            change: Some(DateTime::from_unix(1704067200 + id as i64 * 60)),
+            created_at: None,
+            owner: format!("user{}", id % 5),
+            parent_id: 0,
+            rating: Some([Rating::Safe, Rating::Questionable, Rating::Explicit][id as usize % 3]),
+            sample: id.is_multiple_of(2),
+            sample_height: 300,
+            sample_width: 400,
+            score: (id % 100) as i64 - 10,
+            tags: tags.join(" "),
+            source: String::new(),
+            status: "active".to_string(),
+            has_notes: id.is_multiple_of(10),
+            comment_count: (id % 4) as i64,
+        }
+    }
+
+    /// Urls of all requests, oldest first
+    pub fn requests(&self) -> Vec<Url> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|x| x.into_inner())
+            .clone()
+    }
+
+    /// Client which sends requests to this booru, without retries
+    #[inline]
+    pub fn client(&self) -> R34Client {
+        R34Client::init()
+            .transport(self.clone())
+            .retry(RetryPolicy::none())
+    }
+
+    /// Find posts of `params`, returns count of all matched posts and posts of page
+    ///
+    /// Posts are sorted by `id` from new to old, unless `sort:` metatag is given,
+    /// `page` is ignored when `id` is given
+    pub fn search(&self, params: &R34Params<'_>) -> (usize, Posts) {
+        let query = params.tags().parse::<Query>().ok();
+        let generated = match params.id {
+            Some(id) if self.any_id && !self.posts.iter().any(|x| x.id == id as i64) => {
+                Some(Self::fake_post(id as u64))
+            }
+            _ => None,
+        };
+        let mut found: Vec<&Post> = self
+            .posts
+            .iter()
+            .chain(&generated)
+            .filter(|x| params.id.is_none_or(|id| x.id == id as i64))
+            .filter(|x| query.as_ref().is_none_or(|query| query.matches(x)))
+            .collect();
+        let (key, order) = query
+            .as_ref()
+            .and_then(sort_of)
+            .unwrap_or((SortKey::Id, SortOrder::Desc));
+        found.sort_by(|a, b| match order {
+            SortOrder::Asc => a.cmp_by(b, key),
+            SortOrder::Desc => b.cmp_by(a, key),
+        });
+        let limit = params.limit as usize;
+        // api ignores `pid` when `id` is given
+        let page = match params.id {
+            Some(_) => 0,
+            None => params.page as usize,
+        };
+        let page = found
+            .iter()
+            .skip(page * limit)
+            .take(limit)
+            .map(|x| (*x).clone())
+            .collect::<Vec<_>>();
+        (found.len(), page.into())
+    }
+
+    /// Answer of api for `url`
+    pub fn respond(&self, url: &Url) -> Response {
+        let mut params = R34ParamsOwned::init().limit(100).page(0);
+        let mut json = false;
+        if url
+            .query_pairs()
+            .any(|(key, value)| key == "s" && value == "tag")
+        {
+            return self.respond_tags(url);
+        }
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "tags" => match value.parse::<R34ParamsOwned>() {
+                    Ok(tags) => {
+                        params.positive_tags = tags.positive_tags;
+                        params.negative_tags = tags.negative_tags;
+                        params.query = tags.query;
+                    }
+                    Err(_) => return Response::new(StatusCode::OK, ""),
+                },
+                "limit" => params.limit = value.parse().unwrap_or(100),
+                "pid" => params.page = value.parse().unwrap_or(0),
+                "id" => params.id = value.parse().ok(),
+                "json" => json = value == "1",
+                _ => {}
+            }
+        }
+        params.limit = params.limit.min(R34Params::MAX_LIMIT);
+        let (count, posts) = self.search(&params);
+        if json {
+            // api sends empty body when nothing is found
+            let body = match posts.is_empty() {
+                true => vec![],
+                false => serde_json::to_vec(&posts).unwrap_or_default(),
+            };
+            return Response::new(StatusCode::OK, body).header("Content-Type", "application/json");
+        }
+        Response::new(
+            StatusCode::OK,
+            xml(count, params.page as usize * params.limit as usize, &posts),
+        )
+        .header("Content-Type", "text/xml")
+    }
+}
+
+impl FakeBooru {
+    /// Answer of `s=tag` api, xml with every tag of `names`
+    fn respond_tags(&self, url: &Url) -> Response {
+        let names = url
+            .query_pairs()
+            .find(|(key, _)| key == "names")
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_default();
+        let mut out =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?><tags type=\"array\">");
+        for (id, name) in names.split_whitespace().enumerate() {
+            let code = self.tag_types.get(name).unwrap_or_default().code();
+            out.push_str(&format!(
+                "<tag type=\"{}\" count=\"1\" name=\"{}\" ambiguous=\"false\" id=\"{}\"/>",
+                code,
+                escape(name),
+                id + 1
+            ));
+        }
+        out.push_str("</tags>");
+        Response::new(StatusCode::OK, out).header("Content-Type", "text/xml")
+    }
+}
+
+#[async_trait]
+impl Transport for FakeBooru {
+    async fn send(&self, request: Request) -> Result<Response> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|x| x.into_inner())
+            .push(request.url.clone());
+        Ok(self.respond(&request.url))
+    }
+}
+
+/// Find `sort:` metatag in top level of query
+fn sort_of(query: &Query) -> Option<(SortKey, SortOrder)> {
+    let items = match query {
+        Query::And(items) => items.as_slice(),
+        item => std::slice::from_ref(item),
+    };
+    items.iter().find_map(|item| match item {
+        Query::Meta { key, value } => match MetaTag::parse(key, value) {
+            Ok(MetaTag::Sort(key, order)) => Some((key, order)),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Escape value of xml attribute
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Xml answer of api
+fn xml(count: usize, offset: usize, posts: &Posts) -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><posts count=\"{}\" offset=\"{}\">",
+        count, offset
+    );
+    for post in posts.data_ref() {
+        out.push_str(&format!(
+            "<post id=\"{}\" score=\"{}\" width=\"{}\" height=\"{}\" md5=\"{}\" tags=\" {} \" file_url=\"{}\"/>",
+            post.id,
+            post.score,
+            post.width,
+            post.height,
+            escape(&post.hash),
+            escape(&post.tags),
+            escape(&post.file_url),
+        ));
+    }
+    out.push_str("</posts>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn booru() -> FakeBooru {
+        FakeBooru::init()
+            .json(include_bytes!("../../tests/fixtures/posts.json"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn search() {
+        let client = booru().client();
+        let params = R34Params::init().page(0).limit(10);
+
+        let posts = client
+            .download(&params.clone().positive_tags(vec!["fish"]))
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 2);
+        // new posts go first
+        assert_eq!(posts.data_ref()[0].id, 10542350);
+
+        let posts = client
+            .download(&params.clone().negative_tags(vec!["fish"]))
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+
+        let posts = client.download(&params.clone().id(10542274)).await.unwrap();
+        assert_eq!(
+            posts.get_f_url(),
+            Some(posts.data_ref()[0].file_url.as_str())
+        );
+        assert_eq!(posts.data_ref()[0].id, 10542274);
+
+        let posts = client
+            .download(&params.clone().positive_tags(vec!["whale"]))
+            .await
+            .unwrap();
+        assert!(posts.is_empty());
+
+        let params = params.query("sort:score:asc".parse().unwrap());
+        let posts = client.download(&params).await.unwrap();
+        assert_eq!(posts.data_ref()[0].score, -2);
+    }
+
+    #[tokio::test]
+    async fn pages() {
+        let booru = FakeBooru::init().generate(25);
+        let client = booru.client();
+        let params = R34Params::init().limit(10).page(0);
+        assert_eq!(client.count(&params).await.unwrap(), 25);
+        let posts = client.download(&params.clone().page(2)).await.unwrap();
+        assert_eq!(posts.len(), 5);
+        assert_eq!(client.pages(&params).collect().await.unwrap().len(), 25);
+        assert_eq!(booru.requests().len(), 5);
+    }
+
+    #[test]
+    fn generate() {
+        let booru = booru().generate(3);
+        assert_eq!(booru.posts.len(), 6);
+        assert_eq!(booru.posts[3].id, 10542351);
+        assert_eq!(FakeBooru::fake_post(7), FakeBooru::fake_post(7));
+        assert!(booru.posts[4]
+            .tags
+            .split_whitespace()
+            .all(|x| TAGS.contains(&x)));
+    }
+
+    #[tokio::test]
+    async fn tag_types() {
+        let booru = FakeBooru::init()
+            .tag_type("molly", TagType::Artist)
+            .tag_type("sea", TagType::Copyright);
+        let tags: Vec<String> = (0..150).map(|x| format!("tag_{}", x)).collect();
+        let types = booru
+            .client()
+            .tag_types(
+                tags.iter()
+                    .map(String::as_str)
+                    .chain(["molly", "sea", "molly"]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(types.len(), 152);
+        assert_eq!(types.get("molly"), Some(TagType::Artist));
+        assert_eq!(types.get("sea"), Some(TagType::Copyright));
+        assert_eq!(types.get("tag_7"), Some(TagType::General));
+        // by batches of 100 tags
+        let requests = booru.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .query_pairs()
+            .any(|(key, value)| key == "s" && value == "tag"));
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use uller::Url;
+
+use crate::error::{Error, Result};
+use crate::rules::rule34::credentials::redact_credentials;
+use crate::rules::rule34::time::DateTime;
+
+pub use reqwest::header;
+pub use reqwest::StatusCode;
+
+use header::{HeaderMap, HeaderName, HeaderValue};
+
+/// `User-Agent` of default [ReqwestTransport]
+pub const USER_AGENT: &str = concat!("shuller/", env!("CARGO_PKG_VERSION"));
+
+/// Way to send requests
+///
+/// Every request of shuller goes through it,
+/// implement it to use your own http client, mock or proxy
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::transport::{Request, Response, StatusCode, Transport};
+///
+/// #[derive(Debug)]
+/// struct Empty;
+///
+/// #[async_trait::async_trait]
+/// impl Transport for Empty {
+///     async fn send(&self, _: Request) -> shuller::error::Result<Response> {
+///         Ok(Response::new(StatusCode::OK, "[]"))
+///     }
+/// }
+///
+/// async fn example() {
+///     let client = R34Client::init().transport(Empty);
+///     let posts = client.download(&R34Params::init()).await.unwrap();
+///     assert!(posts.is_empty());
+/// }
+/// ```
+#[async_trait]
+pub trait Transport: Send + Sync + Debug {
+    /// Send `GET` request
+    async fn send(&self, request: Request) -> Result<Response>;
+}
+
+/// `GET` request
+#[derive(Clone, PartialEq, Eq)]
+pub struct Request {
+    pub url: Url,
+    pub headers: HeaderMap,
+}
+
+/// `api_key` and `user_id` of url are hidden
+impl Debug for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Request")
+            .field("url", &redact_credentials(&self.url).as_str())
+            .field("headers", &self.headers)
+            .finish()
+    }
+}
+
+impl Request {
+    /// Make `GET` request without headers
+    #[inline]
+    pub fn get(url: Url) -> Self {
+        Self {
+            url,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Add header, invalid headers are ignored
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+}
+
+/// Response of [Transport]
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+impl Response {
+    /// Make response with whole body
+    #[inline]
+    pub fn new(status: StatusCode, body: impl Into<Bytes>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: Body::Full(Some(body.into())),
+        }
+    }
+
+    /// Add header, invalid headers are ignored
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Get header as [str]
+    pub fn header_str(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|x| x.to_str().ok())
+    }
+
+    /// Time to wait from `Retry-After` header, in seconds or http date
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use shuller::transport::{Response, StatusCode};
+    ///
+    /// let response = Response::new(StatusCode::TOO_MANY_REQUESTS, "").header("Retry-After", "120");
+    /// assert_eq!(response.retry_after(), Some(Duration::from_secs(120)));
+    /// ```
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.header_str(header::RETRY_AFTER.as_str())?.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = parse_http_date(value)?;
+        Some(
+            date.duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+
+    /// Return [Error::Status] if status isn't success, or [Error::Throttled]
+    /// if status is `429` or `503` and server asked to wait by `Retry-After`
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+        let throttled = matches!(
+            self.status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        );
+        match self.retry_after() {
+            Some(retry_after) if throttled => Err(Error::Throttled(self.status, retry_after)),
+            _ => Err(Error::Status(self.status)),
+        }
+    }
+}
+
+/// Parse date like `Sun, 06 Nov 1994 08:49:37 GMT`
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    // same as `Sun Nov 06 08:49:37 +0000 1994`
+    let date: DateTime = format!("_ {} {} {} +0000 {}", month, day, time, year)
+        .parse()
+        .ok()?;
+    date.try_into().ok()
+}
+
+/// Body of [Response], can be read by chunks
+pub enum Body {
+    /// Whole body, [None] after it was read
+    Full(Option<Bytes>),
+    /// Body which is received by chunks
+    Stream(Box<dyn BodyStream>),
+}
+
+impl Body {
+    /// Read next chunk, [None] at the end of body
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        match self {
+            Body::Full(x) => Ok(x.take()),
+            Body::Stream(x) => x.chunk().await,
+        }
+    }
+
+    /// Read whole body
+    pub async fn bytes(mut self) -> Result<Bytes> {
+        if let Body::Full(x) = &mut self {
+            return Ok(x.take().unwrap_or_default());
+        }
+        let mut out = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            out.extend_from_slice(&chunk);
+        }
+        Ok(out.freeze())
+    }
+}
+
+impl Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Body::Full(x) => f.debug_tuple("Full").field(x).finish(),
+            Body::Stream(_) => f.debug_tuple("Stream").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Source of chunks for [Body::Stream]
+#[async_trait]
+pub trait BodyStream: Send {
+    /// Read next chunk, [None] at the end of body
+    async fn chunk(&mut self) -> Result<Option<Bytes>>;
+}
+
+#[async_trait]
+impl BodyStream for reqwest::Response {
+    #[inline]
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        Ok(reqwest::Response::chunk(self).await?)
+    }
+}
+
+/// [Transport] by [reqwest::Client]
+///
+/// Configure timeouts, proxies, headers and pool in [reqwest::ClientBuilder]
+///
+/// ```
+/// use shuller::prelude::*;
+/// use shuller::transport::ReqwestTransport;
+///
+/// let client = reqwest::Client::builder()
+///     .timeout(std::time::Duration::from_secs(10))
+///     .user_agent("my-bot/1.0")
+///     .build()
+///     .unwrap();
+/// let client = R34Client::init().transport(ReqwestTransport::new(client));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Use configured [reqwest::Client]
+    #[inline]
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+/// New [reqwest::Client] with [USER_AGENT], only clones of this transport share its pool
+///
+/// Pooled connections belong to runtime which opened them,
+/// so make transport for every runtime instead of sharing one
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_default();
+        Self::new(client)
+    }
+}
+
+impl From<reqwest::Client> for ReqwestTransport {
+    #[inline]
+    fn from(value: reqwest::Client) -> Self {
+        Self::new(value)
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: Request) -> Result<Response> {
+        let response = self
+            .client
+            .get(request.url)
+            .headers(request.headers)
+            .send()
+            .await?;
+        Ok(Response {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: Body::Stream(Box::new(response)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_request() {
+        let url = Url::parse("https://api/index.php?tags=dark&api_key=secret&user_id=42").unwrap();
+        let debug = format!("{:?}", Request::get(url));
+        assert!(debug.contains("tags=dark"));
+        assert!(!debug.contains("secret"));
+        assert!(!debug.contains("42"));
+    }
+
+    struct Chunks(Vec<&'static str>);
+
+    #[async_trait]
+    impl BodyStream for Chunks {
+        async fn chunk(&mut self) -> Result<Option<Bytes>> {
+            Ok(match self.0.is_empty() {
+                true => None,
+                false => Some(Bytes::from(self.0.remove(0))),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn body() {
+        let body = Body::Stream(Box::new(Chunks(vec!["[", "]"])));
+        assert_eq!(body.bytes().await.unwrap(), Bytes::from("[]"));
+
+        let mut body = Body::Full(Some(Bytes::from("[]")));
+        assert_eq!(body.chunk().await.unwrap(), Some(Bytes::from("[]")));
+        assert_eq!(body.chunk().await.unwrap(), None);
+    }
+
+    #[test]
+    fn http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784111777))
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1709164800))
+        );
+        assert_eq!(parse_http_date("yesterday"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn retry_after() {
+        let response =
+            Response::new(StatusCode::SERVICE_UNAVAILABLE, "").header("Retry-After", "3");
+        assert!(matches!(
+            response.error_for_status(),
+            Err(Error::Throttled(StatusCode::SERVICE_UNAVAILABLE, x)) if x == Duration::from_secs(3)
+        ));
+        let response = Response::new(StatusCode::TOO_MANY_REQUESTS, "")
+            .header("Retry-After", "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(response.retry_after(), Some(Duration::ZERO));
+        // only 429 and 503 are throttled
+        let response = Response::new(StatusCode::FORBIDDEN, "").header("Retry-After", "3");
+        assert!(matches!(
+            response.error_for_status(),
+            Err(Error::Status(StatusCode::FORBIDDEN))
+        ));
+    }
+
+    #[test]
+    fn status() {
+        assert!(Response::new(StatusCode::OK, "").error_for_status().is_ok());
+        assert!(matches!(
+            Response::new(StatusCode::BAD_GATEWAY, "").error_for_status(),
+            Err(Error::Status(StatusCode::BAD_GATEWAY))
+        ));
+    }
+}
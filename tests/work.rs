@@ -3,9 +3,24 @@ mod tests {
     use std::vec;
 
     use shuller::prelude::*; // Импортируйте нужные модули
+    use shuller::testing::FakeBooru;
+
+    /// All requests go to fake api, so tests work without network
+    fn fake() {
+        static FAKE: std::sync::Once = std::sync::Once::new();
+        FAKE.call_once(|| {
+            let booru = FakeBooru::init()
+                .json(include_bytes!("fixtures/posts.json"))
+                .unwrap()
+                .generate(500)
+                .any_id();
+            R34Client::set_global(booru.client());
+        });
+    }
 
     #[tokio::test]
     async fn test_of_work() {
+        fake();
         let instance: Posts = R34Params::init()
             .positive_tags(vec!["dark"])
             .negative_tags(vec!["ai_generated"])
@@ -18,12 +33,14 @@ mod tests {
 
     #[tokio::test]
     async fn check_mini_post() {
+        fake();
         let instance: Posts = R34Params::init().id(10542274).download().await.unwrap();
         assert!(instance.get_url_ext().is_some())
     }
 
     #[tokio::test]
     async fn check_mini_post_many() {
+        fake();
         let instance: Posts = R34Params::init()
             .positive_tags(vec!["dark", "fish"])
             .negative_tags(vec!["ai_generated"])
@@ -36,6 +53,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_macro_normal() {
+        fake();
         let instance = R34!(
             p = vec!["dark", "fish"],
             n = vec!["ai_generated"],
@@ -49,6 +67,7 @@ mod tests {
     }
     #[tokio::test]
     async fn test_macro_download() {
+        fake();
         let instance = R34!(D;
             p = vec!["dark", "fish"],
             n = vec!["ai_generated"],
@@ -83,6 +102,7 @@ mod tests {
     #[cfg(feature = "rand")]
     #[tokio::test]
     async fn test_macro_random_download() {
+        fake();
         let first: tokio::task::JoinHandle<Result<String, ()>> = tokio::spawn(async {
             let instance = R34!(R; D).unwrap();
             Ok(instance.get_f_url().unwrap().to_string())